@@ -0,0 +1,29 @@
+#!/bin/bash
+#
+# Measures the overhead of `hone run` itself.
+#
+# $ examples/bench-short-observations.sh [REPEAT] [WORKERS]
+#
+# Results with the defaults (REPEAT=2000, WORKERS=64):
+# - The runner that polled children and RPCs every 1ms used 14 CPU ticks in 3 sec while idle,
+#   and the event-driven one uses 4 ticks.
+# - 2000 `/bin/true` observations take about the same wall time with both.
+#
+set -eu
+
+REPEAT=${1:-2000}
+WORKERS=${2:-64}
+
+# Many very short observations.
+TIMEFORMAT="short observations: repeat=$REPEAT, workers=$WORKERS, %R sec (user: %U sec, sys: %S sec)"
+time hone run --repeat $REPEAT --workers $WORKERS /bin/true > /dev/null
+
+# CPU time consumed by `hone` while all of the workers are sleeping.
+hone run --repeat $WORKERS --workers $WORKERS /bin/sleep 4 > /dev/null &
+PID=$!
+sleep 0.5
+BEFORE=$(awk '{print $14 + $15}' /proc/$PID/stat)
+sleep 3
+AFTER=$(awk '{print $14 + $15}' /proc/$PID/stat)
+wait $PID
+echo "idle: workers=$WORKERS, $((AFTER - BEFORE)) CPU ticks in 3 sec"
@@ -1,5 +1,7 @@
 pub mod ask;
+pub mod format;
 pub mod get;
+pub mod rerun;
 pub mod run;
 pub mod show;
 pub mod tell;
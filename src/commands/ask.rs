@@ -1,48 +1,167 @@
+use crate::commands::format::{AskedParam, OutputFormat};
 use crate::envvar;
 use crate::param::{
-    CategoricalParamType, ContinousParamType, DiscreteParamType, FidelityParamType,
-    NormalParamType, NumParamType, OrdinalParamType, ParamName, ParamType, StrParamType,
+    CategoricalParamType, ContinousParamType, DiscreteParamType, FidelityParamType, IntParamType,
+    ListParamType, NormalParamType, NumParamType, OrdinalParamType, ParamName, ParamType,
+    ParamValue, PermutationParamType, Prior, StrParamType, VectorParamType,
 };
 use crate::rpc;
+use crate::study::TemplateParam;
 use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 #[derive(Debug, clap::Args)]
 pub struct AskOpt {
     pub param_name: String,
 
-    #[clap(long, short = 'l')]
+    /// Shorthand for `--format long-option`.
+    #[clap(long, short = 'l', conflicts_with = "format")]
     pub long_option: bool,
 
+    #[clap(long, value_enum, default_value = "raw")]
+    pub format: OutputFormat,
+
+    /// The number of decimal places of numeric values.
+    #[clap(long)]
+    pub precision: Option<usize>,
+
+    /// The value used when not running under `hone run`.
+    ///
+    /// If omitted, the value in the JSON file specified by `HONE_DEFAULTS` is used,
+    /// or else the first choice, the midpoint of the range or the mean.
+    #[clap(long)]
+    pub default: Option<String>,
+
     #[clap(subcommand)]
     pub param_spec: ParamSpec,
 }
 
 impl AskOpt {
     pub fn ask(&self) -> anyhow::Result<String> {
-        let observation_id = envvar::get_observation_id()?;
         let param_type = self
             .param_spec
             .to_param_type()
             .with_context(|| format!("the specification of {:?} is invalid", self.param_name))?;
-        let req = rpc::AskReq {
-            observation_id,
-            param_name: ParamName::new(self.param_name.clone()),
-            param_type,
+        let res = if envvar::is_standalone() {
+            default_value(&self.param_name, &param_type, self.default.as_deref())?
+        } else {
+            let observation_id = envvar::get_observation_id()?;
+            let req = rpc::AskReq {
+                observation_id,
+                param_name: ParamName::new(self.param_name.clone()),
+                param_type: param_type.clone(),
+            };
+            rpc::call::<rpc::AskRpc>(req)?
         };
-        let res = rpc::call::<rpc::AskRpc>(req)?;
-        let v = res.to_string();
-        if self.long_option {
-            if matches!(self.param_spec, ParamSpec::Bool) && v == "true" {
-                Ok(format!("--{}", self.param_name))
-            } else {
-                Ok(format!("--{}={:?}", self.param_name, v))
-            }
+        let format = if self.long_option {
+            OutputFormat::LongOption
         } else {
-            Ok(v)
+            self.format
+        };
+        let asked = AskedParam {
+            name: self.param_name.clone(),
+            ty: param_type,
+            value: res,
+        };
+        Ok(format.format(&[asked], self.precision))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AskManyOpt {
+    /// A JSON file containing an array of parameter specifications.
+    #[clap(long)]
+    pub spec: Option<PathBuf>,
+
+    #[clap(long, value_enum, default_value = "env")]
+    pub format: OutputFormat,
+
+    /// The number of decimal places of numeric values.
+    #[clap(long)]
+    pub precision: Option<usize>,
+
+    /// Parameter specifications such as `lr=range 0.0001 1 --ln`.
+    pub params: Vec<TemplateParam>,
+}
+
+impl AskManyOpt {
+    pub fn ask(&self) -> anyhow::Result<String> {
+        let mut params = Vec::new();
+        if let Some(path) = &self.spec {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("Cannot open the spec file: path={:?}", path))?;
+            let specs: Vec<String> = serde_json::from_reader(std::io::BufReader::new(file))
+                .with_context(|| format!("Invalid spec file: path={:?}", path))?;
+            for spec in specs {
+                params.push(spec.parse::<TemplateParam>()?);
+            }
         }
+        params.extend(self.params.iter().cloned());
+
+        let values = if envvar::is_standalone() {
+            params
+                .iter()
+                .map(|p| default_value(p.name.get(), &p.ty, None))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        } else {
+            let req = rpc::AskManyReq {
+                observation_id: envvar::get_observation_id()?,
+                params: params
+                    .iter()
+                    .map(|p| (p.name.clone(), p.ty.clone()))
+                    .collect(),
+            };
+            rpc::call::<rpc::AskManyRpc>(req)?
+        };
+
+        let asked = params
+            .into_iter()
+            .zip(values)
+            .map(|(p, value)| AskedParam {
+                name: p.name.get().to_owned(),
+                ty: p.ty,
+                value,
+            })
+            .collect::<Vec<_>>();
+        Ok(self.format.format(&asked, self.precision))
     }
 }
 
+fn bool_param_type() -> ParamType {
+    ParamType::Str(StrParamType::Categorical(
+        CategoricalParamType::new(vec!["false".to_owned(), "true".to_owned()], None)
+            .expect("unreachable"),
+    ))
+}
+
+/// Returns the value used when not running under `hone run`.
+fn default_value(
+    name: &str,
+    param_type: &ParamType,
+    explicit: Option<&str>,
+) -> anyhow::Result<ParamValue> {
+    let value = if let Some(v) = explicit {
+        ParamValue::Str(v.to_owned())
+    } else if let Some(path) = std::env::var_os(envvar::KEY_DEFAULTS) {
+        let file = std::fs::File::open(&path)
+            .with_context(|| format!("Cannot open the defaults file: path={:?}", path))?;
+        let mut defaults: BTreeMap<String, ParamValue> =
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .with_context(|| format!("Invalid defaults file: path={:?}", path))?;
+        if let Some(v) = defaults.remove(name) {
+            v
+        } else {
+            return param_type.default_value();
+        }
+    } else {
+        return param_type.default_value();
+    };
+    param_type
+        .normalize(&value)
+        .with_context(|| format!("invalid default value for {:?}", name))
+}
+
 #[derive(Debug, clap::Subcommand)]
 pub enum ParamSpec {
     Bool,
@@ -50,39 +169,126 @@ pub enum ParamSpec {
         choices: Vec<String>,
         #[clap(long)]
         ordinal: bool,
+        /// Relative probabilities of the choices (e.g., `choice a b c --weights 3 1 1`).
+        #[clap(long, num_args = 1..)]
+        weights: Option<Vec<f64>>,
     },
     Range {
         min: f64,
         max: f64,
         #[clap(long)]
         ln: bool,
+        /// The interval of the values (`max - min` must be a multiple of it).
         #[clap(long)]
         step: Option<f64>,
         #[clap(long)]
         fidelity: bool,
+        /// A normal distribution truncated to the range (log-normal with `--ln`, where MEAN is the median
+        /// and STDDEV is in log scale).
+        #[clap(long, num_args = 2, value_names = ["MEAN", "STDDEV"], allow_negative_numbers = true)]
+        prior: Option<Vec<f64>>,
+    },
+    Int {
+        #[clap(allow_negative_numbers = true)]
+        min: i64,
+        #[clap(allow_negative_numbers = true)]
+        max: i64,
+        #[clap(long)]
+        ln: bool,
     },
     Normal {
         mean: f64,
         stddev: f64,
     },
+    /// A list of `size` values of the element type (e.g., `vector 4 range 16 512 --step 16`).
+    Vector {
+        size: usize,
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        element: Vec<String>,
+    },
+    /// An ordering of the items (e.g., `permutation a b c d`).
+    Permutation {
+        #[clap(required = true)]
+        items: Vec<String>,
+    },
+}
+
+impl std::str::FromStr for ParamSpec {
+    type Err = anyhow::Error;
+
+    /// Parses a whitespace separated specification such as `range 0.0001 1 --ln`.
+    ///
+    /// Arguments containing whitespace can be quoted as in a shell (e.g., `choice 'a b' "c d"`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_args(split_args(s)?)
+    }
+}
+
+/// Splits `s` into arguments at whitespace, handling single and double quotes and backslash escapes.
+fn split_args(s: &str) -> anyhow::Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (Some(q), c) if q == c => quote = None,
+            (None, '\\') | (Some('"'), '\\') => {
+                let c = chars
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("trailing backslash in {:?}", s))?;
+                arg.get_or_insert_with(String::new).push(c);
+            }
+            (_, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        anyhow::bail!("unterminated quote in {:?}", s);
+    }
+    args.extend(arg);
+    Ok(args)
 }
 
 impl ParamSpec {
-    fn to_param_type(&self) -> anyhow::Result<ParamType> {
+    fn parse_args<I, T>(args: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        #[derive(Debug, clap::Parser)]
+        #[clap(no_binary_name = true)]
+        struct Parser {
+            #[clap(subcommand)]
+            spec: ParamSpec,
+        }
+
+        let parser = <Parser as clap::Parser>::try_parse_from(args).map_err(|e| {
+            let message = e.render().to_string();
+            anyhow::anyhow!("{}", message.trim().trim_start_matches("error: "))
+        })?;
+        Ok(parser.spec)
+    }
+
+    pub fn to_param_type(&self) -> anyhow::Result<ParamType> {
         match self {
-            Self::Bool => CategoricalParamType::new(vec!["false".to_owned(), "true".to_owned()])
-                .map(StrParamType::Categorical)
-                .map(ParamType::Str),
+            Self::Bool => Ok(bool_param_type()),
             Self::Choice {
                 choices,
                 ordinal: false,
-            } => CategoricalParamType::new(choices.clone())
+                weights,
+            } => CategoricalParamType::new(choices.clone(), weights.clone())
                 .map(StrParamType::Categorical)
                 .map(ParamType::Str),
             Self::Choice {
                 choices,
                 ordinal: true,
-            } => OrdinalParamType::new(choices.clone())
+                weights,
+            } => OrdinalParamType::new(choices.clone(), weights.clone())
                 .map(StrParamType::Ordinal)
                 .map(ParamType::Str),
             Self::Normal { mean, stddev } => NormalParamType::new(*mean, *stddev)
@@ -94,37 +300,133 @@ impl ParamSpec {
                 ln,
                 step: None,
                 fidelity: false,
-            } => ContinousParamType::new(*min, *max, *ln)
+                prior,
+            } => ContinousParamType::new(*min, *max, *ln, to_prior(prior)?)
                 .map(NumParamType::Continous)
                 .map(ParamType::Num),
             Self::Range {
                 min,
                 max,
-                ln: false,
+                ln,
                 step: Some(step),
                 fidelity: false,
-            } => DiscreteParamType::new(*min, *max, *step)
+                prior,
+            } => DiscreteParamType::new(*min, *max, *step, *ln, to_prior(prior)?)
                 .map(NumParamType::Discrete)
                 .map(ParamType::Num),
+            Self::Int { min, max, ln } => IntParamType::new(*min, *max, *ln)
+                .map(NumParamType::Int)
+                .map(ParamType::Num),
             Self::Range {
                 min,
                 max,
                 ln: false,
                 step,
                 fidelity: true,
+                prior: None,
             } => FidelityParamType::new(*min, *max, *step)
                 .map(NumParamType::Fidelity)
                 .map(ParamType::Num),
             Self::Range {
                 ln: true,
-                step: Some(_),
+                fidelity: true,
                 ..
-            } => anyhow::bail!("Cannot specify both `--ln` and `--step` options."),
+            } => anyhow::bail!("Cannot specify both `--ln` and `--fidelity` options."),
             Self::Range {
-                ln: true,
                 fidelity: true,
+                prior: Some(_),
                 ..
-            } => anyhow::bail!("Cannot specify both `--ln` and `--fidelity` options."),
+            } => anyhow::bail!("Cannot specify both `--prior` and `--fidelity` options."),
+            Self::Vector { size, element } => {
+                let element = Self::parse_args(element)?.to_param_type()?;
+                VectorParamType::new(*size, element)
+                    .map(ListParamType::Vector)
+                    .map(ParamType::List)
+            }
+            Self::Permutation { items } => PermutationParamType::new(items.clone())
+                .map(ListParamType::Permutation)
+                .map(ParamType::List),
+        }
+    }
+}
+
+fn to_prior(prior: &Option<Vec<f64>>) -> anyhow::Result<Option<Prior>> {
+    prior.as_ref().map(|p| Prior::new(p[0], p[1])).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_args_handles_quotes() -> anyhow::Result<()> {
+        assert_eq!(split_args("  range  0 1 ")?, ["range", "0", "1"]);
+        assert_eq!(
+            split_args(r#"choice 'a b' "c d" e\ f "" 'g"h' "i\"j""#)?,
+            ["choice", "a b", "c d", "e f", "", "g\"h", "i\"j"]
+        );
+        assert!(split_args("choice 'a b").is_err());
+        assert!(split_args("choice a\\").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_choice_with_spaces() -> anyhow::Result<()> {
+        let spec: ParamSpec = "choice 'adam w' sgd --weights 3 1".parse()?;
+        match spec.to_param_type()? {
+            ParamType::Str(StrParamType::Categorical(ty)) => {
+                assert_eq!(ty.choices().get(), ["adam w", "sgd"]);
+            }
+            ty => panic!("unexpected type: {:?}", ty),
         }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_param_specs() -> anyhow::Result<()> {
+        assert!(matches!(
+            "bool".parse::<ParamSpec>()?.to_param_type()?,
+            ParamType::Str(StrParamType::Categorical(_))
+        ));
+        assert!(matches!(
+            "choice a b --ordinal"
+                .parse::<ParamSpec>()?
+                .to_param_type()?,
+            ParamType::Str(StrParamType::Ordinal(_))
+        ));
+        assert!(matches!(
+            "range 1 64 --step 1 --ln"
+                .parse::<ParamSpec>()?
+                .to_param_type()?,
+            ParamType::Num(NumParamType::Discrete(_))
+        ));
+        assert!(matches!(
+            "range 1 10 --fidelity"
+                .parse::<ParamSpec>()?
+                .to_param_type()?,
+            ParamType::Num(NumParamType::Fidelity(_))
+        ));
+        assert!(matches!(
+            "int -5 5".parse::<ParamSpec>()?.to_param_type()?,
+            ParamType::Num(NumParamType::Int(_))
+        ));
+        assert!(matches!(
+            "vector 3 range 16 512 --step 16"
+                .parse::<ParamSpec>()?
+                .to_param_type()?,
+            ParamType::List(ListParamType::Vector(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_param_specs() -> anyhow::Result<()> {
+        assert!("unknown 1 2".parse::<ParamSpec>().is_err());
+        assert!("range 0".parse::<ParamSpec>().is_err());
+        let conflicts = "range 1 10 --ln --fidelity".parse::<ParamSpec>()?;
+        assert!(conflicts.to_param_type().is_err());
+        let empty_range = "range 10 1".parse::<ParamSpec>()?;
+        assert!(empty_range.to_param_type().is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,247 @@
+use crate::envvar;
+use crate::param::{ListParamType, NumParamType, ParamName, ParamType, ParamValue, StrParamType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Values only.
+    Raw,
+
+    /// `--NAME=VALUE` command-line options (`--NAME` for a `true` bool).
+    LongOption,
+
+    /// `-NAME VALUE` command-line options (`-NAME` for a `true` bool).
+    ShortOption,
+
+    /// `export HONE_PARAM_NAME=VALUE` lines (the same variables as the ones of `hone run --param`).
+    Env,
+
+    /// A JSON object.
+    Json,
+
+    /// `NAME=VALUE` overrides of Hydra.
+    Hydra,
+}
+
+#[derive(Debug, Clone)]
+pub struct AskedParam {
+    pub name: String,
+    pub ty: ParamType,
+    pub value: ParamValue,
+}
+
+impl AskedParam {
+    fn is_true(&self) -> bool {
+        let is_bool = matches!(
+            &self.ty,
+            ParamType::Str(StrParamType::Categorical(t)) if t.choices().get() == ["false", "true"]
+        );
+        is_bool && self.value == ParamValue::Str("true".to_owned())
+    }
+
+    fn format_value(&self, precision: Option<usize>, separator: &str) -> String {
+        format_value(&self.ty, &self.value, precision, separator)
+    }
+
+    fn to_json(&self, precision: Option<usize>) -> serde_json::Value {
+        to_json(&self.ty, &self.value, precision)
+    }
+
+    /// Formats the value as a Hydra override value (e.g., `[16,32]` for a list).
+    fn hydra_value(&self, precision: Option<usize>) -> String {
+        if let ParamValue::List(vs) = &self.value {
+            let ty = element_type(&self.ty);
+            let vs = vs
+                .iter()
+                .map(|v| hydra_quote(&format_value(ty, v, precision, ",")))
+                .collect::<Vec<_>>();
+            format!("[{}]", vs.join(","))
+        } else {
+            hydra_quote(&self.format_value(precision, ","))
+        }
+    }
+}
+
+/// Formats a value.
+///
+/// Numeric values are printed with `precision` decimal places if specified, or else values of a discrete
+/// range are printed with the number of decimal places of the step (e.g., `3` for `range 1 100 --step 1`).
+/// The elements of a list are joined with `separator`.
+fn format_value(
+    ty: &ParamType,
+    value: &ParamValue,
+    precision: Option<usize>,
+    separator: &str,
+) -> String {
+    let v = match value {
+        ParamValue::Str(v) => return v.clone(),
+        ParamValue::Int(v) => return v.to_string(),
+        ParamValue::Num(v) => v.get(),
+        ParamValue::List(vs) => {
+            return vs
+                .iter()
+                .map(|v| format_value(element_type(ty), v, precision, separator))
+                .collect::<Vec<_>>()
+                .join(separator);
+        }
+    };
+    let step_decimals = match ty {
+        ParamType::Num(NumParamType::Discrete(t)) => {
+            Some(decimals(t.step().get()).max(decimals(t.range().min().get())))
+        }
+        _ => None,
+    };
+    if let Some(precision) = precision.or(step_decimals) {
+        format!("{:.*}", precision, v)
+    } else {
+        v.to_string()
+    }
+}
+
+fn to_json(ty: &ParamType, value: &ParamValue, precision: Option<usize>) -> serde_json::Value {
+    if let ParamValue::List(vs) = value {
+        let vs = vs.iter().map(|v| to_json(element_type(ty), v, precision));
+        return serde_json::Value::Array(vs.collect());
+    }
+
+    let s = format_value(ty, value, precision, ",");
+    match value {
+        ParamValue::Str(_) | ParamValue::List(_) => serde_json::Value::String(s),
+        ParamValue::Int(v) => serde_json::json!(v),
+        ParamValue::Num(_) => serde_json::from_str(&s).unwrap_or(serde_json::Value::String(s)),
+    }
+}
+
+/// Returns the type of the elements if `ty` is a vector.
+fn element_type(ty: &ParamType) -> &ParamType {
+    match ty {
+        ParamType::List(ListParamType::Vector(t)) => t.element(),
+        _ => ty,
+    }
+}
+
+impl OutputFormat {
+    pub fn format(self, params: &[AskedParam], precision: Option<usize>) -> String {
+        if self == Self::Json {
+            let map = params
+                .iter()
+                .map(|p| (p.name.clone(), p.to_json(precision)))
+                .collect::<serde_json::Map<_, _>>();
+            return serde_json::Value::Object(map).to_string();
+        }
+
+        let items = params
+            .iter()
+            .map(|p| {
+                let separator = if self == Self::Raw { " " } else { "," };
+                let value = p.format_value(precision, separator);
+                match self {
+                    Self::LongOption if p.is_true() => quote(&format!("--{}", p.name)),
+                    Self::LongOption => quote(&format!("--{}={}", p.name, value)),
+                    Self::ShortOption if p.is_true() => quote(&format!("-{}", p.name)),
+                    Self::ShortOption => {
+                        format!("{} {}", quote(&format!("-{}", p.name)), quote(&value))
+                    }
+                    Self::Env => {
+                        let key = envvar::param_key(&ParamName::new(p.name.clone()));
+                        format!("export {}={}", key, quote(&value))
+                    }
+                    Self::Hydra => quote(&format!("{}={}", p.name, p.hydra_value(precision))),
+                    Self::Raw | Self::Json => value,
+                }
+            })
+            .collect::<Vec<_>>();
+        match self {
+            Self::Raw | Self::Env => items.join("\n"),
+            _ => items.join(" "),
+        }
+    }
+}
+
+/// Quotes a string for POSIX shells if it contains any special characters.
+pub fn quote(s: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !s.is_empty() && s.chars().all(is_safe) {
+        s.to_owned()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// Quotes a value of a Hydra override if it contains characters that have special meanings in Hydra.
+fn hydra_quote(s: &str) -> String {
+    let is_safe = |c: char| c.is_alphanumeric() || "_@%+:./-".contains(c);
+    if !s.is_empty() && s.chars().all(is_safe) {
+        s.to_owned()
+    } else {
+        format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"))
+    }
+}
+
+/// Returns the number of decimal places of the shortest representation of `v`.
+fn decimals(v: f64) -> usize {
+    let s = v.to_string();
+    s.find('.').map_or(0, |i| s.len() - i - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::DiscreteParamType;
+    use crate::types::FiniteF64;
+
+    fn num(v: f64) -> ParamValue {
+        ParamValue::Num(FiniteF64::new(v).expect("finite"))
+    }
+
+    #[test]
+    fn quote_only_special_strings() {
+        assert_eq!(quote("abc-1.5"), "abc-1.5");
+        assert_eq!(quote("--lr=0.1"), "--lr=0.1");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("$HOME"), "'$HOME'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn hydra_quote_escapes_quotes() {
+        assert_eq!(hydra_quote("adam"), "adam");
+        assert_eq!(hydra_quote("a,b"), "'a,b'");
+        assert_eq!(hydra_quote(r"it's\"), r"'it\'s\\'");
+    }
+
+    #[test]
+    fn precision_applies_to_discrete_values() -> anyhow::Result<()> {
+        let ty = ParamType::Num(NumParamType::Discrete(DiscreteParamType::new(
+            1.0, 100.0, 1.0, false, None,
+        )?));
+        assert_eq!(format_value(&ty, &num(3.0), None, ","), "3");
+        assert_eq!(format_value(&ty, &num(3.0), Some(2), ","), "3.00");
+
+        let ty = ParamType::Num(NumParamType::Discrete(DiscreteParamType::new(
+            0.0, 1.0, 0.25, false, None,
+        )?));
+        assert_eq!(format_value(&ty, &num(0.5), None, ","), "0.50");
+        assert_eq!(format_value(&ty, &num(0.5), Some(0), ","), "0");
+        Ok(())
+    }
+
+    #[test]
+    fn env_uses_the_variables_exported_by_run() -> anyhow::Result<()> {
+        let ty = ParamType::Num(NumParamType::Discrete(DiscreteParamType::new(
+            1.0, 100.0, 1.0, false, None,
+        )?));
+        let asked = AskedParam {
+            name: "batch-size".to_owned(),
+            ty,
+            value: num(32.0),
+        };
+        let key = envvar::param_key(&ParamName::new(asked.name.clone()));
+        assert_eq!(key, "HONE_PARAM_BATCH_SIZE");
+        assert_eq!(
+            OutputFormat::Env.format(&[asked], None),
+            format!("export {}=32", key)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::event::{Event, EventReader, ObservationEvent, StudyEvent};
+use crate::param::{ParamName, ParamValue};
+use crate::runner::{StudyRunner, StudyRunnerOpt};
+use crate::study::StudySpec;
+use crate::trial::TrialId;
+use crate::tuners::fix::FixedParam;
+use crate::tuners::replay::ReplayTuner;
+use crate::tuners::Tuner;
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, clap::Args)]
+pub struct RerunOpt {
+    /// The ID of the trial to be rerun.
+    #[clap(long)]
+    pub trial: u64,
+
+    /// Overrides a recorded parameter (e.g., `epochs=100`).
+    #[clap(long)]
+    pub fix: Vec<FixedParam>,
+
+    /// Appends the events to this file instead of writing them to the standard output.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+
+    /// Seconds to wait after sending `SIGTERM` to a killed observation before sending `SIGKILL`.
+    #[clap(long, default_value = "10")]
+    pub kill_grace_period: f64,
+
+    /// The event log of the study that contains the trial.
+    pub log: PathBuf,
+}
+
+impl RerunOpt {
+    pub fn rerun(&self) -> anyhow::Result<()> {
+        let (mut study, mut params) = self
+            .load_trial()
+            .with_context(|| format!("Cannot load a study: path={:?}", self.log))?;
+        for p in &self.fix {
+            params.insert(p.name.clone(), p.value.clone());
+        }
+
+        study.id = uuid::Uuid::new_v4();
+        study
+            .attrs
+            .insert("rerun.trial".to_owned(), self.trial.to_string());
+        let opt = StudyRunnerOpt {
+            kill_grace_period: Duration::try_from_secs_f64(self.kill_grace_period)?,
+            ..StudyRunnerOpt::new(study)
+        };
+        let tuner = Tuner::new(ReplayTuner::new(TrialId::new(self.trial), params));
+
+        if let Some(path) = &self.output {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Cannot open the output file: path={:?}", path))?;
+            self.run(file, opt, tuner)
+        } else {
+            let stdout = std::io::stdout();
+            self.run(stdout.lock(), opt, tuner)
+        }
+    }
+
+    fn run<W: Write>(&self, output: W, opt: StudyRunnerOpt, tuner: Tuner) -> anyhow::Result<()> {
+        StudyRunner::with_tuner(output, opt, tuner)?.run()
+    }
+
+    // Returns the last study that contains the trial, and the parameters of the last observation of it.
+    fn load_trial(&self) -> anyhow::Result<(StudySpec, BTreeMap<ParamName, ParamValue>)> {
+        let file = std::fs::File::open(&self.log)?;
+        let mut reader = EventReader::new(BufReader::new(file));
+        let mut current = None;
+        let mut found = None;
+        while let Some(event) = reader.read()? {
+            match event {
+                Event::Study(StudyEvent::Defined { spec }) => {
+                    current = Some(spec);
+                }
+                Event::Observation(ObservationEvent::Finished { obs, .. })
+                    if obs.trial_id.get() == self.trial =>
+                {
+                    if let Some(spec) = &current {
+                        let params = obs
+                            .params
+                            .into_iter()
+                            .map(|(name, p)| (name, p.value))
+                            .collect();
+                        found = Some((spec.clone(), params));
+                    }
+                }
+                _ => {}
+            }
+        }
+        found.ok_or_else(|| anyhow::anyhow!("no finished observation of the trial {}", self.trial))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventWriter;
+    use crate::metric::MetricName;
+    use crate::study::{CommandSpec, ObservationSpec};
+    use crate::trial::{Observation, ObservationId};
+
+    #[test]
+    fn rerun_extracts_the_metrics_of_the_original_study() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log = dir.path().join("log.jsonl");
+        let output = dir.path().join("rerun.jsonl");
+
+        let study = StudySpec {
+            name: "test".to_owned(),
+            id: uuid::Uuid::new_v4(),
+            attrs: BTreeMap::new(),
+            tuner: Default::default(),
+            fixed_params: BTreeMap::new(),
+            constraints: Vec::new(),
+            command: CommandSpec {
+                path: PathBuf::from("sh"),
+                args: vec!["-c".to_owned(), "echo loss=0.25".to_owned()],
+                params: Vec::new(),
+            },
+            observation: ObservationSpec {
+                metric_patterns: vec!["minimize:loss=loss=([0-9.]+)".parse()?],
+                ..Default::default()
+            },
+        };
+        let mut writer = EventWriter::new(std::fs::File::create(&log)?);
+        writer.write(Event::study_defined(study))?;
+        let mut obs = Observation::new(ObservationId::new(0), TrialId::new(0));
+        obs.exit_status = Some(0);
+        writer.write(Event::observation_finished(obs, Duration::from_secs(1)))?;
+        drop(writer);
+
+        RerunOpt {
+            trial: 0,
+            fix: Vec::new(),
+            output: Some(output.clone()),
+            kill_grace_period: 1.0,
+            log,
+        }
+        .rerun()?;
+
+        let mut reader = EventReader::new(BufReader::new(std::fs::File::open(&output)?));
+        let mut metrics = Vec::new();
+        while let Some(event) = reader.read()? {
+            if let Event::Observation(ObservationEvent::Finished { obs, .. }) = event {
+                metrics.push(obs.metrics[&MetricName::new("loss".to_owned())].value.get());
+            }
+        }
+        assert_eq!(metrics, [0.25]);
+        Ok(())
+    }
+}
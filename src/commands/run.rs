@@ -1,12 +1,22 @@
 use crate::attr::Attr;
+use crate::commands::ask::ParamSpec;
 use crate::event::EventReader;
-use crate::runner::{StudyRunner, StudyRunnerOpt};
-use crate::study::{CommandSpec, StudySpec};
+use crate::param::ParamName;
+use crate::runner::{
+    DedupeMode, MetricMatches, MetricPattern, MetricThreshold, ParamRename, ResourceLimits,
+    StopConditions, StudyRunner, StudyRunnerOpt, WorkerResource,
+};
+use crate::study::{CommandSpec, ObservationSpec, StudySpec, TemplateParam};
+use crate::tuners::constraint::ParamConstraint;
+use crate::tuners::enqueue::EnqueuedParams;
+use crate::tuners::fix::FixedParam;
 use crate::tuners::TunerSpec;
+use crate::types::{ByteSize, ElapsedSeconds};
 use anyhow::Context;
 use std::io::{BufReader, Write};
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, clap::Args)]
 pub struct RunOpt {
@@ -19,15 +29,141 @@ pub struct RunOpt {
     #[clap(long, default_value = "1")]
     pub workers: NonZeroUsize,
 
+    /// Environment variable assigned to each worker slot (e.g., `CUDA_VISIBLE_DEVICES=0,1,2,3`).
+    ///
+    /// The worker of slot `HONE_WORKER_ID=i` is given the `i`-th value (modulo the number of values).
+    #[clap(long)]
+    pub resource: Vec<WorkerResource>,
+
     #[clap(long, short = 'n')]
     pub repeat: Option<usize>,
 
+    /// Stops when an observation satisfies the condition (e.g., `loss<=0.01`).
+    #[clap(long)]
+    pub stop_when: Vec<MetricThreshold>,
+
+    /// Stops starting new trials after this number of consecutive trials without improvement of any objective.
+    ///
+    /// The running trials are finished (and resumed if the tuner requests it).
+    #[clap(long)]
+    pub patience: Option<usize>,
+
+    /// Stops starting new trials when this number of trials have started.
+    #[clap(long)]
+    pub max_trials: Option<usize>,
+
+    /// Stops (and kills running observations) when this time has elapsed (e.g., `8h`).
+    #[clap(long)]
+    pub time_budget: Option<ElapsedSeconds>,
+
+    /// Seconds to wait after sending `SIGTERM` to a killed observation before sending `SIGKILL`.
+    ///
+    /// The signals are sent to the whole process group of the observation.
+    /// Note that if `hone` itself is killed by `SIGKILL`, only the direct child process of
+    /// each observation is killed (by the parent-death signal), and its descendants keep running.
+    #[clap(long, default_value = "10")]
+    pub kill_grace_period: f64,
+
+    /// Maximum resident memory of each observation (e.g., `8G`).
+    ///
+    /// The total of all the processes in the process group of the observation is checked every 100ms,
+    /// and the group is killed by `SIGKILL` when it exceeds the limit.
+    #[clap(long)]
+    pub limit_memory: Option<ByteSize>,
+
+    /// Maximum CPU time of each observation process (e.g., `3600`, `90m` or `1h`).
+    ///
+    /// This is an rlimit, so it applies to each process separately, not to the whole process tree.
+    #[clap(long)]
+    pub limit_cpu_time: Option<ElapsedSeconds>,
+
+    /// Maximum number of file descriptors that each observation process can open.
+    ///
+    /// This is an rlimit, so it applies to each process separately, not to the whole process tree.
+    #[clap(long)]
+    pub limit_open_files: Option<u64>,
+
+    /// Extracts a metric from each line of the output of observations
+    /// (e.g., `minimize:loss=Test set: Average loss: ([0-9.]+)`).
+    ///
+    /// The first capture group is parsed as the value, and the last match wins
+    /// (see `--metric-matches` to also record the other ones).
+    #[clap(long)]
+    pub metric: Vec<MetricPattern>,
+
+    /// How the matches of `--metric` patterns are recorded.
+    ///
+    /// `last` records only the last match as the metric value, and `all` also records every match
+    /// in order as the intermediate values of the metric (`intermediate_metrics` in the output).
+    #[clap(long, value_enum, default_value = "last")]
+    pub metric_matches: MetricMatches,
+
+    /// Minimizes the wall time of each observation, reported as the metric `elapsed`.
+    ///
+    /// A metric with the same name reported by the observation is overwritten.
+    #[clap(long)]
+    pub minimize_elapsed: bool,
+
+    /// Records the exit code of each observation as the metric `exit_code`.
+    ///
+    /// A metric with the same name reported by the observation is overwritten.
+    #[clap(long)]
+    pub metric_exit_code: bool,
+
+    /// Avoids re-evaluating a configuration identical to an already succeeded observation.
+    ///
+    /// `resample` asks the tuner again, and `cache` reuses the metrics of the previous observation
+    /// (the new observation is recorded with `cached` set to the ID of the reused one).
+    /// In the cache mode, the command isn't run if the `--param` values are a duplicate.
+    /// A duplicate completed by the parameters asked at runtime keeps its own result,
+    /// and is recorded with `duplicate_of` set to the ID of the earlier one.
+    /// The study stops after 1000 consecutive trials finished from the cache.
+    /// Deterministic tuners (`sobol`, `halton`, `lhs` and `pbt`) can't be used with `resample`.
+    #[clap(long, value_enum)]
+    pub dedupe: Option<DedupeMode>,
+
     #[clap(long)]
     pub load: Vec<PathBuf>,
 
+    /// Study log whose observations are told to the tuner as prior knowledge.
+    #[clap(long)]
+    pub warm_start: Vec<PathBuf>,
+
+    /// Renames a parameter of the `--warm-start` studies (e.g., `learning_rate=lr`).
+    #[clap(long)]
+    pub rename: Vec<ParamRename>,
+
     #[clap(long)]
     pub tuner: Option<TunerSpec>,
 
+    /// Parameters evaluated before the tuner proposes any (e.g., `{"lr":0.01,"opt":"adam"}`).
+    #[clap(long)]
+    pub enqueue: Vec<EnqueuedParams>,
+
+    /// JSON Lines file of parameters evaluated like `--enqueue` (one object per line).
+    #[clap(long)]
+    pub enqueue_file: Vec<PathBuf>,
+
+    /// Parameter pinned to a value instead of being asked to the tuner (e.g., `lr=0.01`).
+    #[clap(long)]
+    pub fix: Vec<FixedParam>,
+
+    /// Constraint between numeric parameters (e.g., `a + b <= 1`).
+    ///
+    /// A value violating the constraint is re-asked to the tuner (up to 1000 times),
+    /// together with the other parameters of the constraint that haven't been asked yet.
+    /// Deterministic tuners (`sobol`, `halton`, `lhs` and `pbt`) can't be used with constraints,
+    /// and the parameters of a constraint must be numeric.
+    #[clap(long)]
+    pub param_constraint: Vec<ParamConstraint>,
+
+    /// Parameter asked before spawning the command (e.g., `lr=range 0.0001 1 --ln`).
+    ///
+    /// `{lr}` in the arguments is replaced by the value, which is also exported as `HONE_PARAM_LR`.
+    /// Values containing whitespace can be quoted (e.g., `opt=choice 'adam w' sgd`).
+    #[clap(long)]
+    pub param: Vec<TemplateParam>,
+
     pub command: PathBuf,
     pub args: Vec<String>,
 }
@@ -37,7 +173,19 @@ impl RunOpt {
         let command = CommandSpec {
             path: self.command.clone(),
             args: self.args.clone(),
+            params: self.param.clone(),
         };
+        let mut tuner = self.tuner.clone().unwrap_or_default();
+        for params in &self.enqueue {
+            tuner.enqueue(params.clone());
+        }
+        for path in &self.enqueue_file {
+            for params in EnqueuedParams::load_jsonl(path)
+                .with_context(|| format!("Cannot load enqueued parameters: path={:?}", path))?
+            {
+                tuner.enqueue(params);
+            }
+        }
         let study = StudySpec {
             name: self
                 .study_name
@@ -50,13 +198,40 @@ impl RunOpt {
                 .cloned()
                 .map(|a| (a.key, a.value))
                 .collect(),
-            tuner: self.tuner.clone().unwrap_or_default(),
+            tuner,
+            fixed_params: self
+                .fix
+                .iter()
+                .cloned()
+                .map(|p| (p.name, p.value))
+                .collect(),
+            constraints: self.param_constraint.clone(),
             command,
+            observation: ObservationSpec {
+                limits: ResourceLimits {
+                    memory: self.limit_memory,
+                    cpu_time: self.limit_cpu_time,
+                    open_files: self.limit_open_files,
+                },
+                metric_patterns: self.metric.clone(),
+                metric_matches: self.metric_matches,
+                minimize_elapsed: self.minimize_elapsed,
+                metric_exit_code: self.metric_exit_code,
+            },
         };
         let opt = StudyRunnerOpt {
             study,
             workers: self.workers,
             repeat: self.repeat,
+            kill_grace_period: Duration::try_from_secs_f64(self.kill_grace_period)?,
+            resources: self.resource.clone(),
+            stop: StopConditions {
+                stop_when: self.stop_when.clone(),
+                patience: self.patience,
+                max_trials: self.max_trials,
+                time_budget: self.time_budget,
+            },
+            dedupe: self.dedupe,
         };
 
         let stdout = std::io::stdout();
@@ -69,6 +244,12 @@ impl RunOpt {
             self.load(&mut runner, path)
                 .with_context(|| format!("Cannot load a study: path={:?}", path))?;
         }
+        for path in &self.warm_start {
+            let file = std::fs::File::open(path)?;
+            runner
+                .warm_start(EventReader::new(BufReader::new(file)), &self.rename)
+                .with_context(|| format!("Cannot warm-start from a study: path={:?}", path))?;
+        }
         runner.run()
     }
 
@@ -78,3 +259,38 @@ impl RunOpt {
         Ok(())
     }
 }
+
+impl std::str::FromStr for TemplateParam {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, spec) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("No specification part in a parameter: {:?}", s))?;
+        let ty = spec
+            .parse::<ParamSpec>()?
+            .to_param_type()
+            .map_err(|e| anyhow::anyhow!("the specification of {:?} is invalid: {}", name, e))?;
+        Ok(Self {
+            name: ParamName::new(name.to_owned()),
+            ty,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{NumParamType, ParamType};
+
+    #[test]
+    fn parse_template_param() -> anyhow::Result<()> {
+        let p: TemplateParam = "lr=range 0.0001 1 --ln".parse()?;
+        assert_eq!(p.name.get(), "lr");
+        assert!(matches!(p.ty, ParamType::Num(NumParamType::Continous(_))));
+
+        assert!("lr".parse::<TemplateParam>().is_err());
+        assert!("lr=range 1 0".parse::<TemplateParam>().is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::envvar;
 use crate::metric::{MetricName, MetricType, MetricValue};
 use crate::rpc;
+use std::io::Write;
 
 #[derive(Debug, clap::Subcommand)]
 pub enum TellOpt {
@@ -23,12 +24,16 @@ pub enum TellOpt {
 
 impl TellOpt {
     pub fn tell(&self) -> anyhow::Result<()> {
-        let observation_id = envvar::get_observation_id()?;
         let (name, ty, value) = match self {
             Self::Minimize { name, value } => (name, MetricType::Minimize, value),
             Self::Maximize { name, value } => (name, MetricType::Maximize, value),
             Self::Record { name, value } => (name, MetricType::Record, value),
         };
+        if envvar::is_standalone() {
+            return Self::log(name, ty, MetricValue::new(*value)?);
+        }
+
+        let observation_id = envvar::get_observation_id()?;
         let req = rpc::TellReq {
             observation_id,
             metric_name: MetricName::new(name.clone()),
@@ -38,4 +43,19 @@ impl TellOpt {
         rpc::call::<rpc::TellRpc>(req)?;
         Ok(())
     }
+
+    /// Writes the metric to the standard error, or appends it to the file specified by `HONE_TELL_LOG`.
+    fn log(name: &str, ty: MetricType, value: MetricValue) -> anyhow::Result<()> {
+        let json = serde_json::json!({"name": name, "ty": ty, "value": value});
+        if let Some(path) = std::env::var_os(envvar::KEY_TELL_LOG) {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            writeln!(file, "{}", json)?;
+        } else {
+            eprintln!("{}", json);
+        }
+        Ok(())
+    }
 }
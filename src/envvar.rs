@@ -1,3 +1,4 @@
+use crate::param::ParamName;
 use crate::trial::{ObservationId, TrialId};
 use std::net::SocketAddr;
 
@@ -5,6 +6,9 @@ pub const KEY_SERVER_ADDR: &str = "HONE_SERVER_ADDR";
 pub const KEY_STUDY_ID: &str = "HONE_STUDY_INSTANCE_ID";
 pub const KEY_TRIAL_ID: &str = "HONE_TRIAL_ID";
 pub const KEY_OBSERVATION_ID: &str = "HONE_OBSERVATION_ID";
+pub const KEY_WORKER_ID: &str = "HONE_WORKER_ID";
+pub const KEY_DEFAULTS: &str = "HONE_DEFAULTS";
+pub const KEY_TELL_LOG: &str = "HONE_TELL_LOG";
 pub const KEY_STUDY_DIR: &str = "HONE_STUDY_DIR";
 pub const KEY_TRIAL_DIR: &str = "HONE_TRIAL_DIR";
 pub const KEY_OBSERVATION_DIR: &str = "HONE_OBS_DIR";
@@ -12,6 +16,28 @@ pub const KEY_STUDY_TEMP_DIR: &str = "HONE_STUDY_TEMP_DIR";
 pub const KEY_TRIAL_TEMP_DIR: &str = "HONE_TRIAL_TEMP_DIR";
 pub const KEY_OBSERVATION_TEMP_DIR: &str = "HONE_OBS_TEMP_DIR";
 
+/// Returns `true` if the current process isn't spawned by `hone run`.
+pub fn is_standalone() -> bool {
+    std::env::var_os(KEY_SERVER_ADDR).is_none()
+}
+
+/// Returns `HONE_PARAM_<NAME>` where `<NAME>` is the upper-cased parameter name
+/// with non-alphanumeric characters replaced by `_`.
+pub fn param_key(name: &ParamName) -> String {
+    let name = name
+        .get()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("HONE_PARAM_{}", name)
+}
+
 pub fn get_server_addr() -> Result<SocketAddr, EnvVarError> {
     let value = std::env::var(KEY_SERVER_ADDR)
         .map_err(|e| EnvVarError::from_var_error(KEY_SERVER_ADDR, e))?;
@@ -4,6 +4,7 @@ use crate::types::ElapsedSeconds;
 use std::io::{BufRead, Write};
 use std::time::Duration;
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Event {
@@ -31,6 +32,10 @@ impl Event {
         Self::Study(StudyEvent::Defined { spec })
     }
 
+    pub fn study_finished(reason: StopReason) -> Event {
+        Self::Study(StudyEvent::Finished { reason })
+    }
+
     pub fn trial_started(trial_id: TrialId) -> Event {
         Self::Trial(TrialEvent::Started { trial_id })
     }
@@ -61,12 +66,32 @@ impl Event {
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum StudyEvent {
     Started,
     Defined {
         #[serde(flatten)]
         spec: StudySpec,
     },
+    Finished {
+        reason: StopReason,
+    },
+}
+
+/// The reason why a study run has ended.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    Repeat,
+    TunerFinished,
+    TargetReached {
+        condition: String,
+    },
+    Patience,
+    MaxTrials,
+    TimeBudget,
+    /// Every recent trial was a duplicate of a finished observation (see `--dedupe cache`).
+    Exhausted,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
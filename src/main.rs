@@ -1,10 +1,13 @@
 use clap::Parser;
 
 #[derive(Parser)]
+#[allow(clippy::large_enum_variant)]
 enum Opt {
     Ask(hone::commands::ask::AskOpt),
+    AskMany(hone::commands::ask::AskManyOpt),
     #[clap(subcommand)]
     Get(hone::commands::get::GetOpt),
+    Rerun(hone::commands::rerun::RerunOpt),
     Run(hone::commands::run::RunOpt),
     #[clap(subcommand)]
     Show(hone::commands::show::ShowOpt),
@@ -22,9 +25,16 @@ fn main() -> anyhow::Result<()> {
             let value = opt.ask()?;
             println!("{}", value);
         }
+        Opt::AskMany(opt) => {
+            let values = opt.ask()?;
+            println!("{}", values);
+        }
         Opt::Tell(opt) => {
             opt.tell()?;
         }
+        Opt::Rerun(opt) => {
+            opt.rerun()?;
+        }
         Opt::Run(opt) => {
             opt.run()?;
         }
@@ -1,5 +1,7 @@
+use crate::stats::{inverse_normal_cdf, normal_cdf};
 use crate::types::{FiniteF64, InclusiveRange, NonEmptyVec, NonNegF64};
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ParamName(String);
@@ -38,11 +40,83 @@ impl ParamInstance {
     }
 }
 
+// Deserializing `ParamType` directly as an untagged enum discards the validation errors of the variants,
+// so all the variants are flattened into one externally tagged enum instead.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TaggedParamType {
+    Categorical(CategoricalParamType),
+    Ordinal(OrdinalParamType),
+    Continous(ContinousParamType),
+    Discrete(DiscreteParamType),
+    Int(IntParamType),
+    Normal(NormalParamType),
+    Fidelity(FidelityParamType),
+    Vector(VectorParamType),
+    Permutation(PermutationParamType),
+}
+
+impl From<TaggedParamType> for ParamType {
+    fn from(from: TaggedParamType) -> Self {
+        match from {
+            TaggedParamType::Categorical(t) => Self::Str(StrParamType::Categorical(t)),
+            TaggedParamType::Ordinal(t) => Self::Str(StrParamType::Ordinal(t)),
+            TaggedParamType::Continous(t) => Self::Num(NumParamType::Continous(t)),
+            TaggedParamType::Discrete(t) => Self::Num(NumParamType::Discrete(t)),
+            TaggedParamType::Int(t) => Self::Num(NumParamType::Int(t)),
+            TaggedParamType::Normal(t) => Self::Num(NumParamType::Normal(t)),
+            TaggedParamType::Fidelity(t) => Self::Num(NumParamType::Fidelity(t)),
+            TaggedParamType::Vector(t) => Self::List(ListParamType::Vector(t)),
+            TaggedParamType::Permutation(t) => Self::List(ListParamType::Permutation(t)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(untagged, rename_all = "snake_case")]
+#[serde(untagged, rename_all = "snake_case", from = "TaggedParamType")]
 pub enum ParamType {
     Str(StrParamType),
     Num(NumParamType),
+    List(ListParamType),
+}
+
+impl ParamType {
+    pub fn normalize(&self, value: &ParamValue) -> anyhow::Result<ParamValue> {
+        match self {
+            Self::Str(t) => t.normalize(value).map(ParamValue::Str),
+            Self::Num(t) => t.normalize(value),
+            Self::List(t) => t.normalize(value).map(ParamValue::List),
+        }
+    }
+
+    /// Returns a representative value of this type
+    /// (the first or heaviest choice, the median of a range, the mean, or the maximum fidelity).
+    ///
+    /// A vector repeats the value of its element, and a permutation keeps the order of the items.
+    pub fn default_value(&self) -> anyhow::Result<ParamValue> {
+        let v = match self {
+            Self::Str(t) => {
+                let i = t.weights().map_or(0, |weights| {
+                    (0..weights.len()).fold(0, |i, j| if weights[j] > weights[i] { j } else { i })
+                });
+                return Ok(ParamValue::Str(t.choices().get()[i].clone()));
+            }
+            Self::List(ListParamType::Vector(t)) => {
+                let v = t.element().default_value()?;
+                return Ok(ParamValue::List(vec![v; t.size()]));
+            }
+            Self::List(ListParamType::Permutation(t)) => {
+                let items = t.items().get().iter().cloned().map(ParamValue::Str);
+                return Ok(ParamValue::List(items.collect()));
+            }
+            Self::Num(NumParamType::Continous(t)) => t.from_unit(0.5),
+            Self::Num(NumParamType::Discrete(t)) => t.from_unit(0.5),
+            Self::Num(NumParamType::Int(t)) => return Ok(ParamValue::Int(t.from_unit(0.5))),
+            Self::Num(NumParamType::Normal(t)) => t.mean().get(),
+            Self::Num(NumParamType::Fidelity(t)) => t.range().max().get(),
+        };
+        Ok(ParamValue::Num(FiniteF64::new(v)?))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -52,40 +126,231 @@ pub enum StrParamType {
     Ordinal(OrdinalParamType),
 }
 
+impl StrParamType {
+    pub fn choices(&self) -> &NonEmptyVec<String> {
+        match self {
+            Self::Categorical(t) => t.choices(),
+            Self::Ordinal(t) => t.choices(),
+        }
+    }
+
+    pub fn weights(&self) -> Option<&[NonNegF64]> {
+        match self {
+            Self::Categorical(t) => t.weights(),
+            Self::Ordinal(t) => t.weights(),
+        }
+    }
+
+    /// Maps `u` in the interval `[0, 1)` to a choice.
+    ///
+    /// Each choice gets a sub-interval proportional to its weight (or an equal one if not weighted).
+    pub fn choice_from_unit(&self, u: f64) -> &String {
+        let choices = self.choices().get();
+        let i = if let Some(weights) = self.weights() {
+            let total = weights.iter().map(|w| w.get()).sum::<f64>();
+            let mut threshold = u * total;
+            weights
+                .iter()
+                .position(|w| {
+                    threshold -= w.get();
+                    threshold < 0.0 && w.get() > 0.0
+                })
+                .unwrap_or_else(|| weights.iter().rposition(|w| w.get() > 0.0).unwrap_or(0))
+        } else {
+            ((u * choices.len() as f64) as usize).min(choices.len() - 1)
+        };
+        &choices[i]
+    }
+
+    fn normalize(&self, value: &ParamValue) -> anyhow::Result<String> {
+        normalize_choice(self.choices().get(), value)
+    }
+}
+
+fn normalize_choice(choices: &[String], value: &ParamValue) -> anyhow::Result<String> {
+    let choice = match value {
+        ParamValue::Str(v) => choices.iter().find(|c| *c == v),
+        ParamValue::Num(v) => choices
+            .iter()
+            .find(|c| c.parse::<f64>().ok() == Some(v.get())),
+        ParamValue::Int(v) => choices
+            .iter()
+            .find(|c| c.parse::<f64>().ok() == Some(*v as f64)),
+        ParamValue::List(_) => None,
+    };
+    choice
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("{} isn't one of {:?}", value, choices))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UncheckedCategoricalParamType {
+    choices: Vec<String>,
+    #[serde(default)]
+    weights: Option<Vec<f64>>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", try_from = "UncheckedCategoricalParamType")]
 pub struct CategoricalParamType {
     choices: NonEmptyVec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weights: Option<Vec<NonNegF64>>,
+}
+
+impl TryFrom<UncheckedCategoricalParamType> for CategoricalParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedCategoricalParamType) -> Result<Self, Self::Error> {
+        let (choices, weights) = merge_duplicate_choices(from.choices, from.weights);
+        Self::new(choices, weights)
+    }
 }
 
 impl CategoricalParamType {
-    pub fn new(choices: Vec<String>) -> anyhow::Result<Self> {
+    pub fn new(choices: Vec<String>, weights: Option<Vec<f64>>) -> anyhow::Result<Self> {
+        ensure_unique_choices(&choices)?;
+        let weights = weights.map(|w| check_weights(&choices, w)).transpose()?;
         Ok(Self {
             choices: NonEmptyVec::new(choices)?,
+            weights,
         })
     }
 
     pub fn choices(&self) -> &NonEmptyVec<String> {
         &self.choices
     }
+
+    pub fn weights(&self) -> Option<&[NonNegF64]> {
+        self.weights.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UncheckedOrdinalParamType {
+    choices: Vec<String>,
+    #[serde(default)]
+    weights: Option<Vec<f64>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", try_from = "UncheckedOrdinalParamType")]
 pub struct OrdinalParamType {
     choices: NonEmptyVec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weights: Option<Vec<NonNegF64>>,
+}
+
+impl TryFrom<UncheckedOrdinalParamType> for OrdinalParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedOrdinalParamType) -> Result<Self, Self::Error> {
+        let (choices, weights) = merge_duplicate_choices(from.choices, from.weights);
+        Self::new(choices, weights)
+    }
 }
 
 impl OrdinalParamType {
-    pub fn new(choices: Vec<String>) -> anyhow::Result<Self> {
+    pub fn new(choices: Vec<String>, weights: Option<Vec<f64>>) -> anyhow::Result<Self> {
+        ensure_unique_choices(&choices)?;
+        let weights = weights.map(|w| check_weights(&choices, w)).transpose()?;
         Ok(Self {
             choices: NonEmptyVec::new(choices)?,
+            weights,
         })
     }
 
     pub fn choices(&self) -> &NonEmptyVec<String> {
         &self.choices
     }
+
+    pub fn weights(&self) -> Option<&[NonNegF64]> {
+        self.weights.as_deref()
+    }
+}
+
+fn ensure_unique_choices(choices: &[String]) -> anyhow::Result<()> {
+    for (i, c) in choices.iter().enumerate() {
+        anyhow::ensure!(
+            !choices[..i].contains(c),
+            "duplicate choice {:?} in {:?}",
+            c,
+            choices
+        );
+    }
+    Ok(())
+}
+
+/// Merges duplicate choices into the first one, summing their weights
+/// (an unweighted choice counts as weight `1`, so the sampling probabilities are kept).
+///
+/// Duplicate choices are rejected by the constructors but were accepted by older versions,
+/// so this is applied when deserializing to keep the logs recorded by them loadable.
+fn merge_duplicate_choices(
+    choices: Vec<String>,
+    weights: Option<Vec<f64>>,
+) -> (Vec<String>, Option<Vec<f64>>) {
+    let has_duplicates = ensure_unique_choices(&choices).is_err();
+    let weights_len = weights.as_ref().map_or(choices.len(), |w| w.len());
+    if !has_duplicates || weights_len != choices.len() {
+        return (choices, weights);
+    }
+
+    let weights = weights.unwrap_or_else(|| vec![1.0; choices.len()]);
+    let mut merged = Vec::<(String, f64)>::new();
+    for (c, w) in choices.into_iter().zip(weights) {
+        if let Some((_, total)) = merged.iter_mut().find(|(m, _)| *m == c) {
+            *total += w;
+        } else {
+            merged.push((c, w));
+        }
+    }
+    let (choices, weights) = merged.into_iter().unzip();
+    (choices, Some(weights))
+}
+
+fn check_weights(choices: &[String], weights: Vec<f64>) -> anyhow::Result<Vec<NonNegF64>> {
+    anyhow::ensure!(
+        weights.len() == choices.len(),
+        "the number of weights ({}) differs from the number of choices ({})",
+        weights.len(),
+        choices.len()
+    );
+    let weights = weights
+        .into_iter()
+        .map(NonNegF64::new)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    anyhow::ensure!(
+        weights.iter().any(|w| w.get() > 0.0),
+        "at least one weight must be positive"
+    );
+    Ok(weights)
+}
+
+/// Returns the number of `step`s in `width` if it is a multiple of `step`, tolerating rounding errors
+/// (e.g., `0.9 / 0.1` is slightly less than `9`).
+///
+/// The tolerance is relative to the count so that values far from the minimum are also accepted.
+fn step_count(width: f64, step: f64) -> Option<f64> {
+    let n = width / step;
+    let rounded = n.round();
+    if (n - rounded).abs() <= 1e-9 * rounded.max(1.0) {
+        Some(rounded)
+    } else {
+        None
+    }
+}
+
+fn ensure_step_aligned(range: InclusiveRange, step: NonNegF64) -> anyhow::Result<()> {
+    anyhow::ensure!(step.get() > 0.0, "step={} must be positive", step.get());
+    anyhow::ensure!(
+        step_count(range.width().get(), step.get()).is_some(),
+        "max={} isn't a multiple of the step={} from min={}",
+        range.max().get(),
+        step.get(),
+        range.min().get()
+    );
+    Ok(())
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -93,24 +358,124 @@ impl OrdinalParamType {
 pub enum NumParamType {
     Continous(ContinousParamType),
     Discrete(DiscreteParamType),
+    Int(IntParamType),
     Normal(NormalParamType),
     Fidelity(FidelityParamType),
 }
 
+impl NumParamType {
+    fn normalize(&self, value: &ParamValue) -> anyhow::Result<ParamValue> {
+        let v = match value {
+            ParamValue::Num(v) => *v,
+            ParamValue::Int(v) => FiniteF64::new(*v as f64)?,
+            ParamValue::Str(v) => {
+                let v: f64 = v
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("{:?} isn't a number", v))?;
+                FiniteF64::new(v)?
+            }
+            ParamValue::List(_) => anyhow::bail!("{:?} isn't a number", value.to_string()),
+        };
+        let (range, step) = match self {
+            Self::Continous(t) => (Some(t.range()), None),
+            Self::Discrete(t) => (Some(t.range()), Some(t.step())),
+            Self::Int(t) => {
+                anyhow::ensure!(v.get().fract() == 0.0, "{} isn't an integer", v.get());
+                let n = v.get() as i64;
+                anyhow::ensure!(
+                    t.min() <= n && n <= t.max(),
+                    "{} is out of the range {}..={}",
+                    n,
+                    t.min(),
+                    t.max()
+                );
+                return Ok(ParamValue::Int(n));
+            }
+            Self::Normal(_) => (None, None),
+            Self::Fidelity(t) => (Some(t.range()), t.step()),
+        };
+        if let Some(range) = range {
+            anyhow::ensure!(
+                range.contains(v),
+                "{} is out of the range {}..={}",
+                v.get(),
+                range.min().get(),
+                range.max().get()
+            );
+            if let Some(step) = step {
+                anyhow::ensure!(
+                    step_count(v.get() - range.min().get(), step.get()).is_some(),
+                    "{} isn't a multiple of the step {} from {}",
+                    v.get(),
+                    step.get(),
+                    range.min().get()
+                );
+            }
+        }
+        Ok(ParamValue::Num(v))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct UncheckedContinousParamType {
+    range: InclusiveRange,
+    ln: bool,
+    #[serde(default)]
+    prior: Option<Prior>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")] // TOOD: try_from UncheckedContinousParamType
+#[serde(rename_all = "snake_case", try_from = "UncheckedContinousParamType")]
 pub struct ContinousParamType {
     range: InclusiveRange,
     ln: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prior: Option<Prior>,
+}
+
+impl TryFrom<UncheckedContinousParamType> for ContinousParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedContinousParamType) -> Result<Self, Self::Error> {
+        Self::new(
+            from.range.min().get(),
+            from.range.max().get(),
+            from.ln,
+            from.prior,
+        )
+    }
 }
 
 impl ContinousParamType {
-    pub fn new(min: f64, max: f64, ln: bool) -> anyhow::Result<Self> {
+    pub fn new(min: f64, max: f64, ln: bool, prior: Option<Prior>) -> anyhow::Result<Self> {
         let range = InclusiveRange::new(min, max)?;
         if ln {
-            anyhow::ensure!(range.min().get().is_sign_positive(), "TODO");
+            anyhow::ensure!(
+                range.min().get() > 0.0,
+                "min={} must be positive for `--ln`",
+                range.min().get()
+            );
+        }
+        if let Some(prior) = prior {
+            prior.check(ln)?;
+        }
+        Ok(Self { range, ln, prior })
+    }
+
+    /// Maps `u` in the interval `[0, 1]` to a value (in log scale if `ln` is `true`).
+    pub fn from_unit(&self, u: f64) -> f64 {
+        let (min, max) = (self.range.min().get(), self.range.max().get());
+        if let Some(prior) = self.prior {
+            prior.quantile(u, min, max, self.ln)
+        } else if self.ln {
+            (min.ln() + u * (max.ln() - min.ln())).exp().clamp(min, max)
+        } else {
+            (min + u * (max - min)).clamp(min, max)
         }
-        Ok(Self { range, ln })
+    }
+
+    pub const fn prior(&self) -> Option<Prior> {
+        self.prior
     }
 
     pub const fn range(&self) -> InclusiveRange {
@@ -122,13 +487,98 @@ impl ContinousParamType {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct UncheckedPrior {
+    mean: f64,
+    stddev: f64,
+}
+
+/// A normal distribution truncated to the range of a parameter.
+///
+/// If the range is in log scale, this is a log-normal distribution
+/// whose median is `mean` and whose standard deviation in log scale is `stddev`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "UncheckedPrior")]
+pub struct Prior {
+    mean: FiniteF64,
+    stddev: NonNegF64,
+}
+
+impl TryFrom<UncheckedPrior> for Prior {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedPrior) -> Result<Self, Self::Error> {
+        Self::new(from.mean, from.stddev)
+    }
+}
+
+impl Prior {
+    pub fn new(mean: f64, stddev: f64) -> anyhow::Result<Self> {
+        let stddev = NonNegF64::new(stddev)?;
+        anyhow::ensure!(
+            stddev.get() > 0.0,
+            "the stddev={} of a prior must be positive",
+            stddev.get()
+        );
+        Ok(Self {
+            mean: FiniteF64::new(mean)?,
+            stddev,
+        })
+    }
+
+    pub const fn mean(&self) -> FiniteF64 {
+        self.mean
+    }
+
+    pub const fn stddev(&self) -> NonNegF64 {
+        self.stddev
+    }
+
+    fn check(&self, ln: bool) -> anyhow::Result<()> {
+        if ln {
+            anyhow::ensure!(
+                self.mean.get() > 0.0,
+                "the mean={} of a log-normal prior must be positive",
+                self.mean.get()
+            );
+        }
+        Ok(())
+    }
+
+    /// Maps `u` in the interval `[0, 1]` to a value in `min..=max` by the inverse CDF.
+    fn quantile(&self, u: f64, min: f64, max: f64, ln: bool) -> f64 {
+        let scale = |x: f64| if ln { x.ln() } else { x };
+        let (mean, stddev) = (scale(self.mean.get()), self.stddev.get());
+        let lower = normal_cdf((scale(min) - mean) / stddev);
+        let upper = normal_cdf((scale(max) - mean) / stddev);
+        let p = (lower + u * (upper - lower)).clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+        let v = mean + stddev * inverse_normal_cdf(p);
+        let v = if ln { v.exp() } else { v };
+        v.clamp(min, max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct UncheckedNormalParamType {
+    mean: f64,
+    stddev: f64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", try_from = "UncheckedNormalParamType")]
 pub struct NormalParamType {
     mean: FiniteF64,
     stddev: NonNegF64,
 }
 
+impl TryFrom<UncheckedNormalParamType> for NormalParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedNormalParamType) -> Result<Self, Self::Error> {
+        Self::new(from.mean, from.stddev)
+    }
+}
+
 impl NormalParamType {
     pub fn new(mean: f64, stddev: f64) -> anyhow::Result<Self> {
         Ok(Self {
@@ -146,24 +596,101 @@ impl NormalParamType {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct UncheckedDiscreteParamType {
+    range: InclusiveRange,
+    step: f64,
+    #[serde(default)]
+    ln: bool,
+    #[serde(default)]
+    prior: Option<Prior>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", try_from = "UncheckedDiscreteParamType")]
 pub struct DiscreteParamType {
     range: InclusiveRange,
     step: NonNegF64,
+    #[serde(default)]
+    ln: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prior: Option<Prior>,
+}
+
+impl TryFrom<UncheckedDiscreteParamType> for DiscreteParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedDiscreteParamType) -> Result<Self, Self::Error> {
+        Self::new(
+            from.range.min().get(),
+            from.range.max().get(),
+            from.step,
+            from.ln,
+            from.prior,
+        )
+    }
 }
 
 impl DiscreteParamType {
-    pub fn new(min: f64, max: f64, step: f64) -> anyhow::Result<Self> {
-        let mut range = InclusiveRange::new(min, max)?;
-        let max = range.min().get() + (range.width().get() / step).floor() * step;
-        range = InclusiveRange::new(range.min().get(), max)?;
+    pub fn new(
+        min: f64,
+        max: f64,
+        step: f64,
+        ln: bool,
+        prior: Option<Prior>,
+    ) -> anyhow::Result<Self> {
+        let range = InclusiveRange::new(min, max)?;
+        let step = NonNegF64::new(step)?;
+        ensure_step_aligned(range, step)?;
+        if ln {
+            anyhow::ensure!(
+                range.min().get() > 0.0,
+                "min={} must be positive for `--ln`",
+                range.min().get()
+            );
+        }
+        if let Some(prior) = prior {
+            prior.check(ln)?;
+        }
         Ok(Self {
             range,
-            step: NonNegF64::new(step)?,
+            step,
+            ln,
+            prior,
         })
     }
 
+    pub const fn ln(&self) -> bool {
+        self.ln
+    }
+
+    pub const fn prior(&self) -> Option<Prior> {
+        self.prior
+    }
+
+    /// Maps `u` in the interval `[0, 1]` to a value (in log scale if `ln` is `true`).
+    ///
+    /// Like `IntParamType::from_unit`, a value `v` takes the probability of the interval `[v, v + step)`.
+    /// So, if `ln` is `true`, the probability of `v` is proportional to `ln((v + step) / v)`,
+    /// and if a prior is given, it's truncated to `[min, max + step)`.
+    pub fn from_unit(&self, u: f64) -> f64 {
+        let (min, max, step) = (
+            self.range.min().get(),
+            self.range.max().get(),
+            self.step.get(),
+        );
+        let n = if let Some(prior) = self.prior {
+            let v = prior.quantile(u, min, max + step, self.ln);
+            ((v - min) / step).floor() as u64
+        } else if self.ln {
+            let v = (min.ln() + u * ((max + step).ln() - min.ln())).exp();
+            ((v - min) / step).floor() as u64
+        } else {
+            (u * (self.count() + 1) as f64) as u64
+        };
+        min + n.min(self.count()) as f64 * step
+    }
+
     pub const fn range(&self) -> InclusiveRange {
         self.range
     }
@@ -173,28 +700,97 @@ impl DiscreteParamType {
     }
 
     pub fn count(&self) -> u64 {
-        (self.range.width().get() / self.step.get()) as u64
+        step_count(self.range.width().get(), self.step.get()).expect("unreachable") as u64
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct UncheckedIntParamType {
+    min: i64,
+    max: i64,
+    ln: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", try_from = "UncheckedIntParamType")]
+pub struct IntParamType {
+    min: i64,
+    max: i64,
+    ln: bool,
+}
+
+impl TryFrom<UncheckedIntParamType> for IntParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedIntParamType) -> Result<Self, Self::Error> {
+        Self::new(from.min, from.max, from.ln)
+    }
+}
+
+impl IntParamType {
+    pub fn new(min: i64, max: i64, ln: bool) -> anyhow::Result<Self> {
+        anyhow::ensure!(min <= max, "min={} is greater than max={}", min, max);
+        if ln {
+            anyhow::ensure!(min >= 1, "min={} must be positive for `--ln`", min);
+        }
+        Ok(Self { min, max, ln })
+    }
+
+    pub const fn min(&self) -> i64 {
+        self.min
+    }
+
+    pub const fn max(&self) -> i64 {
+        self.max
+    }
+
+    pub const fn ln(&self) -> bool {
+        self.ln
+    }
+
+    /// Maps `u` in the interval `[0, 1]` to a value.
+    ///
+    /// If `ln` is `true`, the probability of `n` is proportional to `ln((n + 1) / n)`.
+    pub fn from_unit(&self, u: f64) -> i64 {
+        let (min, max) = (self.min as f64, self.max as f64);
+        let v = if self.ln {
+            (min.ln() + u * ((max + 1.0).ln() - min.ln())).exp()
+        } else {
+            min + u * (max - min + 1.0)
+        };
+        (v.floor() as i64).clamp(self.min, self.max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct UncheckedFidelityParamType {
+    range: InclusiveRange,
+    step: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "UncheckedFidelityParamType")]
 pub struct FidelityParamType {
     range: InclusiveRange,
     step: Option<NonNegF64>,
 }
 
+impl TryFrom<UncheckedFidelityParamType> for FidelityParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedFidelityParamType) -> Result<Self, Self::Error> {
+        Self::new(from.range.min().get(), from.range.max().get(), from.step)
+    }
+}
+
 impl FidelityParamType {
     pub fn new(min: f64, max: f64, step: Option<f64>) -> anyhow::Result<Self> {
-        let mut range = InclusiveRange::new(min, max)?;
+        let range = InclusiveRange::new(min, max)?;
+        let step = step.map(NonNegF64::new).transpose()?;
         if let Some(step) = step {
-            let max = range.min().get() + (range.width().get() / step).floor() * step;
-            range = InclusiveRange::new(range.min().get(), max)?;
+            ensure_step_aligned(range, step)?;
         }
-        Ok(Self {
-            range,
-            step: step.map(|step| NonNegF64::new(step)).transpose()?,
-        })
+        Ok(Self { range, step })
     }
 
     pub const fn range(&self) -> InclusiveRange {
@@ -206,18 +802,339 @@ impl FidelityParamType {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListParamType {
+    Vector(VectorParamType),
+    Permutation(PermutationParamType),
+}
+
+impl ListParamType {
+    /// Normalizes a list value.
+    ///
+    /// A string is split by commas (e.g., `16,32,64`) so that lists can be given on the command line.
+    fn normalize(&self, value: &ParamValue) -> anyhow::Result<Vec<ParamValue>> {
+        let values = match value {
+            ParamValue::List(vs) => vs.clone(),
+            ParamValue::Str(v) => v
+                .split(',')
+                .map(|v| ParamValue::Str(v.trim().to_owned()))
+                .collect(),
+            v => vec![v.clone()],
+        };
+        match self {
+            Self::Vector(t) => {
+                anyhow::ensure!(
+                    values.len() == t.size(),
+                    "{:?} doesn't have {} elements",
+                    value.to_string(),
+                    t.size()
+                );
+                values.iter().map(|v| t.element().normalize(v)).collect()
+            }
+            Self::Permutation(t) => {
+                let items = t.items().get();
+                let values = values
+                    .iter()
+                    .map(|v| normalize_choice(items, v))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                anyhow::ensure!(
+                    values.len() == items.len() && ensure_unique_choices(&values).is_ok(),
+                    "{:?} isn't a permutation of {:?}",
+                    value.to_string(),
+                    items
+                );
+                Ok(values.into_iter().map(ParamValue::Str).collect())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UncheckedVectorParamType {
+    size: usize,
+    element: Box<ParamType>,
+}
+
+/// A fixed-length list whose elements are independently drawn from `element`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "UncheckedVectorParamType")]
+pub struct VectorParamType {
+    size: usize,
+    element: Box<ParamType>,
+}
+
+impl TryFrom<UncheckedVectorParamType> for VectorParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedVectorParamType) -> Result<Self, Self::Error> {
+        Self::new(from.size, *from.element)
+    }
+}
+
+impl VectorParamType {
+    pub fn new(size: usize, element: ParamType) -> anyhow::Result<Self> {
+        anyhow::ensure!(size > 0, "the size of a vector must be positive");
+        anyhow::ensure!(
+            !matches!(element, ParamType::List(_)),
+            "the element of a vector must not be a list"
+        );
+        Ok(Self {
+            size,
+            element: Box::new(element),
+        })
+    }
+
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn element(&self) -> &ParamType {
+        &self.element
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UncheckedPermutationParamType {
+    items: Vec<String>,
+}
+
+/// An ordering of all the `items`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "UncheckedPermutationParamType")]
+pub struct PermutationParamType {
+    items: NonEmptyVec<String>,
+}
+
+impl TryFrom<UncheckedPermutationParamType> for PermutationParamType {
+    type Error = anyhow::Error;
+
+    fn try_from(from: UncheckedPermutationParamType) -> Result<Self, Self::Error> {
+        // Duplicate items were accepted by older versions (see `merge_duplicate_choices`).
+        let mut items = Vec::new();
+        for item in from.items {
+            if !items.contains(&item) {
+                items.push(item);
+            }
+        }
+        Self::new(items)
+    }
+}
+
+impl PermutationParamType {
+    pub fn new(items: Vec<String>) -> anyhow::Result<Self> {
+        ensure_unique_choices(&items)?;
+        Ok(Self {
+            items: NonEmptyVec::new(items)?,
+        })
+    }
+
+    pub fn items(&self) -> &NonEmptyVec<String> {
+        &self.items
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(untagged, rename_all = "snake_case")]
 pub enum ParamValue {
     Str(String),
+    Int(i64),
     Num(FiniteF64),
+    List(Vec<ParamValue>),
+}
+
+impl ParamValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Str(_) | Self::List(_) => None,
+            Self::Int(v) => Some(*v as f64),
+            Self::Num(v) => Some(v.get()),
+        }
+    }
 }
 
 impl std::fmt::Display for ParamValue {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::Str(v) => write!(f, "{}", v),
+            Self::Int(v) => write!(f, "{}", v),
             Self::Num(v) => write!(f, "{}", v.get()),
+            Self::List(vs) => {
+                for (i, v) in vs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                Ok(())
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_num_param_type(rng: &mut StdRng) -> anyhow::Result<ParamType> {
+        let min = rng.gen_range(-100.0, 100.0);
+        let positive_min = rng.gen_range(0.001, 100.0);
+        let width = rng.gen_range(0.0, 1000.0);
+        let step = [0.01, 0.1, 0.25, 1.0, 3.0][rng.gen_range(0, 5)];
+        let steps = f64::from(rng.gen_range(0u32, 1000));
+        let prior = Some(Prior::new(positive_min, rng.gen_range(0.1, 2.0))?).filter(|_| rng.gen());
+        let ty = match rng.gen_range(0, 7) {
+            0 => NumParamType::Continous(ContinousParamType::new(min, min + width, false, prior)?),
+            1 => NumParamType::Continous(ContinousParamType::new(
+                positive_min,
+                positive_min + width,
+                true,
+                prior,
+            )?),
+            2 => NumParamType::Discrete(DiscreteParamType::new(
+                min,
+                min + steps * step,
+                step,
+                false,
+                prior,
+            )?),
+            3 => NumParamType::Discrete(DiscreteParamType::new(
+                positive_min,
+                positive_min + steps * step,
+                step,
+                true,
+                prior,
+            )?),
+            4 => {
+                let min = rng.gen_range(1, 100);
+                NumParamType::Int(IntParamType::new(
+                    min,
+                    min + rng.gen_range(0, 1000),
+                    rng.gen(),
+                )?)
+            }
+            5 => NumParamType::Normal(NormalParamType::new(min, width)?),
+            _ => NumParamType::Fidelity(FidelityParamType::new(
+                min,
+                min + steps * step,
+                Some(step).filter(|_| rng.gen()),
+            )?),
+        };
+        Ok(ParamType::Num(ty))
+    }
+
+    fn random_param_type(rng: &mut StdRng) -> anyhow::Result<ParamType> {
+        let choices = (0..rng.gen_range(1, 5))
+            .map(|i| format!("c{}", i))
+            .collect::<Vec<_>>();
+        let weights =
+            Some(choices.iter().map(|_| rng.gen_range(0.1, 10.0)).collect()).filter(|_| rng.gen());
+        let ty = match rng.gen_range(0, 6) {
+            0 => ParamType::Str(StrParamType::Categorical(CategoricalParamType::new(
+                choices, weights,
+            )?)),
+            1 => ParamType::Str(StrParamType::Ordinal(OrdinalParamType::new(
+                choices, weights,
+            )?)),
+            2 => ParamType::List(ListParamType::Permutation(PermutationParamType::new(
+                choices,
+            )?)),
+            3 => ParamType::List(ListParamType::Vector(VectorParamType::new(
+                rng.gen_range(1, 5),
+                random_num_param_type(rng)?,
+            )?)),
+            _ => random_num_param_type(rng)?,
+        };
+        Ok(ty)
+    }
+
+    #[test]
+    fn param_types_round_trip_through_serde() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let ty = random_param_type(&mut rng)?;
+            let json = serde_json::to_string(&ty)?;
+            let deserialized: ParamType = serde_json::from_str(&json)?;
+            assert_eq!(deserialized, ty, "{}", json);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sampled_num_values_are_normalized_as_is() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let ty = random_num_param_type(&mut rng)?;
+            let u = rng.gen::<f64>();
+            let v = match &ty {
+                ParamType::Num(NumParamType::Continous(t)) => t.from_unit(u),
+                ParamType::Num(NumParamType::Discrete(t)) => t.from_unit(u),
+                ParamType::Num(NumParamType::Int(t)) => t.from_unit(u) as f64,
+                _ => continue,
+            };
+            let value = ParamValue::Num(FiniteF64::new(v)?);
+            let normalized = ty.normalize(&value)?;
+            assert_eq!(normalized.as_f64(), Some(v), "{:?}", ty);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_serialized_param_types() {
+        let invalid = [
+            r#"{"continous":{"range":{"min":0.0,"max":1.0},"ln":true}}"#,
+            r#"{"continous":{"range":{"min":2.0,"max":1.0},"ln":false}}"#,
+            r#"{"discrete":{"range":{"min":0.0,"max":1.0},"step":0.0}}"#,
+            r#"{"discrete":{"range":{"min":0.0,"max":10.0},"step":3.0}}"#,
+            r#"{"fidelity":{"range":{"min":0.0,"max":10.0},"step":3.0}}"#,
+            r#"{"int":{"min":0,"max":10,"ln":true}}"#,
+            r#"{"normal":{"mean":0.0,"stddev":-1.0}}"#,
+            r#"{"categorical":{"choices":[]}}"#,
+            r#"{"categorical":{"choices":["a","b"],"weights":[1.0]}}"#,
+            r#"{"ordinal":{"choices":["a","b"],"weights":[0.0,0.0]}}"#,
+            r#"{"vector":{"size":0,"element":{"int":{"min":0,"max":1,"ln":false}}}}"#,
+        ];
+        for json in &invalid {
+            let e = serde_json::from_str::<ParamType>(json).expect_err(json);
+            assert!(!e.to_string().contains("unknown"), "{}: {}", json, e);
+        }
+    }
+
+    #[test]
+    fn discrete_max_must_be_aligned_to_step() -> anyhow::Result<()> {
+        assert!(DiscreteParamType::new(0.0, 10.0, 3.0, false, None).is_err());
+        assert!(FidelityParamType::new(0.0, 10.0, Some(3.0)).is_err());
+
+        let t = DiscreteParamType::new(0.1, 0.9, 0.1, false, None)?;
+        assert_eq!(t.count(), 8);
+        let t = DiscreteParamType::new(0.0, 1e9, 0.1, false, None)?;
+        assert_eq!(t.count(), 10_000_000_000);
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_choices_in_old_logs_are_merged() -> anyhow::Result<()> {
+        assert!(CategoricalParamType::new(vec!["a".to_owned(), "a".to_owned()], None).is_err());
+
+        let json = r#"{"categorical":{"choices":["a","b","a"]}}"#;
+        let ty: ParamType = serde_json::from_str(json)?;
+        assert_eq!(ty.default_value()?, ParamValue::Str("a".to_owned()));
+        let expected =
+            CategoricalParamType::new(vec!["a".to_owned(), "b".to_owned()], Some(vec![2.0, 1.0]))?;
+        assert_eq!(ty, ParamType::Str(StrParamType::Categorical(expected)));
+
+        let json = r#"{"ordinal":{"choices":["a","b","a"],"weights":[1.0,3.0,0.5]}}"#;
+        let ty: ParamType = serde_json::from_str(json)?;
+        let expected =
+            OrdinalParamType::new(vec!["a".to_owned(), "b".to_owned()], Some(vec![1.5, 3.0]))?;
+        assert_eq!(ty, ParamType::Str(StrParamType::Ordinal(expected)));
+
+        let json = r#"{"permutation":{"items":["a","b","a"]}}"#;
+        let ty: ParamType = serde_json::from_str(json)?;
+        let expected = PermutationParamType::new(vec!["a".to_owned(), "b".to_owned()])?;
+        assert_eq!(ty, ParamType::List(ListParamType::Permutation(expected)));
+        Ok(())
+    }
+}
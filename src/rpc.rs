@@ -7,9 +7,10 @@ use bytecodec::json_codec::{JsonDecoder, JsonEncoder};
 use fibers_rpc::client::ClientServiceBuilder;
 use fibers_rpc::server::ServerBuilder;
 use fibers_rpc::{Call, ProcedureId};
-use futures::{Async, Future, Stream};
+use futures::Future;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::sync::mpsc::Sender;
 
 pub fn init() {
     fibers_global::set_thread_count(1);
@@ -53,6 +54,28 @@ pub struct AskReq {
     pub param_type: ParamType,
 }
 
+#[derive(Debug)]
+pub struct AskManyRpc;
+
+impl Call for AskManyRpc {
+    const ID: ProcedureId = ProcedureId(3);
+    const NAME: &'static str = "ask_many";
+
+    type Req = AskManyReq;
+    type ReqEncoder = JsonEncoder<Self::Req>;
+    type ReqDecoder = JsonDecoder<Self::Req>;
+
+    type Res = Vec<ParamValue>;
+    type ResEncoder = JsonEncoder<Self::Res>;
+    type ResDecoder = JsonDecoder<Self::Res>;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AskManyReq {
+    pub observation_id: ObservationId,
+    pub params: Vec<(ParamName, ParamType)>,
+}
+
 #[derive(Debug)]
 pub struct TellRpc;
 
@@ -106,6 +129,10 @@ pub enum Message {
         req: AskReq,
         reply: fibers::sync::oneshot::Sender<ParamValue>,
     },
+    AskMany {
+        req: AskManyReq,
+        reply: fibers::sync::oneshot::Sender<Vec<ParamValue>>,
+    },
     Tell {
         req: TellReq,
         reply: fibers::sync::oneshot::Sender<()>,
@@ -117,75 +144,86 @@ pub enum Message {
 }
 
 #[derive(Debug)]
-pub struct Channel {
-    rx: fibers::sync::mpsc::Receiver<Message>,
-    pub server_addr: SocketAddr, // TODO: private
-}
-
-impl Channel {
-    pub fn try_recv(&mut self) -> Option<Message> {
-        match self.rx.poll() {
-            Err(()) => unreachable!(),
-            Ok(Async::Ready(None)) => unreachable!(),
-            Ok(Async::NotReady) => None,
-            Ok(Async::Ready(Some(m))) => Some(m),
-        }
+pub struct AskHandler<T> {
+    tx: Sender<T>,
+}
+
+impl<T> fibers_rpc::server::HandleCall<AskRpc> for AskHandler<T>
+where
+    T: 'static + From<Message> + Send,
+{
+    fn handle_call(&self, req: <AskRpc as Call>::Req) -> fibers_rpc::server::Reply<AskRpc> {
+        let (tx, rx) = fibers::sync::oneshot::channel();
+        let _ = self.tx.send(Message::Ask { req, reply: tx }.into());
+        fibers_rpc::server::Reply::future(rx.or_else(|_| dropped_reply()))
     }
 }
 
 #[derive(Debug)]
-pub struct AskHandler {
-    tx: fibers::sync::mpsc::Sender<Message>,
+pub struct AskManyHandler<T> {
+    tx: Sender<T>,
 }
 
-impl fibers_rpc::server::HandleCall<AskRpc> for AskHandler {
-    fn handle_call(&self, req: <AskRpc as Call>::Req) -> fibers_rpc::server::Reply<AskRpc> {
+impl<T> fibers_rpc::server::HandleCall<AskManyRpc> for AskManyHandler<T>
+where
+    T: 'static + From<Message> + Send,
+{
+    fn handle_call(&self, req: <AskManyRpc as Call>::Req) -> fibers_rpc::server::Reply<AskManyRpc> {
         let (tx, rx) = fibers::sync::oneshot::channel();
-        let _ = self.tx.send(Message::Ask { req, reply: tx });
-        // TODO: Don't panic here.
-        fibers_rpc::server::Reply::future(rx.map_err(|e| panic!("Error: {}", e)))
+        let _ = self.tx.send(Message::AskMany { req, reply: tx }.into());
+        fibers_rpc::server::Reply::future(rx.or_else(|_| dropped_reply()))
     }
 }
 
 #[derive(Debug)]
-pub struct TellHandler {
-    tx: fibers::sync::mpsc::Sender<Message>,
+pub struct TellHandler<T> {
+    tx: Sender<T>,
 }
 
-impl fibers_rpc::server::HandleCall<TellRpc> for TellHandler {
+impl<T> fibers_rpc::server::HandleCall<TellRpc> for TellHandler<T>
+where
+    T: 'static + From<Message> + Send,
+{
     fn handle_call(&self, req: <TellRpc as Call>::Req) -> fibers_rpc::server::Reply<TellRpc> {
         let (tx, rx) = fibers::sync::oneshot::channel();
-        let _ = self.tx.send(Message::Tell { req, reply: tx });
-        fibers_rpc::server::Reply::future(rx.map_err(|e| panic!("Error: {}", e)))
+        let _ = self.tx.send(Message::Tell { req, reply: tx }.into());
+        fibers_rpc::server::Reply::future(rx.or_else(|_| dropped_reply()))
     }
 }
 
 #[derive(Debug)]
-pub struct MktempHandler {
-    tx: fibers::sync::mpsc::Sender<Message>,
+pub struct MktempHandler<T> {
+    tx: Sender<T>,
 }
 
-impl fibers_rpc::server::HandleCall<MktempRpc> for MktempHandler {
+impl<T> fibers_rpc::server::HandleCall<MktempRpc> for MktempHandler<T>
+where
+    T: 'static + From<Message> + Send,
+{
     fn handle_call(&self, req: <MktempRpc as Call>::Req) -> fibers_rpc::server::Reply<MktempRpc> {
         let (tx, rx) = fibers::sync::oneshot::channel();
-        let _ = self.tx.send(Message::Mktemp { req, reply: tx });
-        fibers_rpc::server::Reply::future(rx.map_err(|e| panic!("Error: {}", e)))
+        let _ = self.tx.send(Message::Mktemp { req, reply: tx }.into());
+        fibers_rpc::server::Reply::future(rx.or_else(|_| dropped_reply()))
     }
 }
 
-// TODO:
-pub fn spawn_rpc_server() -> anyhow::Result<Channel> {
+/// Leaves a call pending when the runner drops the reply (e.g., the caller is being killed).
+fn dropped_reply<T>() -> futures::future::Empty<T, bytecodec::marker::Never> {
+    futures::future::empty()
+}
+
+/// Spawns an RPC server that forwards the received requests to `tx`.
+pub fn spawn_rpc_server<T>(tx: Sender<T>) -> anyhow::Result<SocketAddr>
+where
+    T: 'static + From<Message> + Send,
+{
     let mut builder = ServerBuilder::new(SocketAddr::from(([127, 0, 0, 1], 0)));
-    let (tx, rx) = fibers::sync::mpsc::channel();
     builder.add_call_handler(AskHandler { tx: tx.clone() });
+    builder.add_call_handler(AskManyHandler { tx: tx.clone() });
     builder.add_call_handler(TellHandler { tx: tx.clone() });
-    builder.add_call_handler(MktempHandler { tx: tx.clone() });
+    builder.add_call_handler(MktempHandler { tx });
     let server = builder.finish(fibers_global::handle());
     let (server, addr) = fibers_global::execute(server.local_addr())?;
     fibers_global::spawn(server.map_err(|e| panic!("{}", e)));
-
-    Ok(Channel {
-        rx,
-        server_addr: addr,
-    })
+    Ok(addr)
 }
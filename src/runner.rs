@@ -1,48 +1,155 @@
 use self::command::CommandRunner;
+use self::dedupe::Dedupe;
+use self::slots::WorkerSlots;
+use self::stop::PatienceCounter;
 use self::tempdir::TempDirs;
-use crate::event::{Event, EventReader, EventWriter};
-use crate::metric::MetricInstance;
+use self::warm_start::WarmStart;
+use crate::event::{Event, EventReader, EventWriter, StopReason};
+use crate::metric::{MetricInstance, MetricName, MetricType, MetricValue};
 use crate::param::{ParamInstance, ParamValue};
 use crate::rpc;
 use crate::study::StudySpec;
-use crate::trial::{Observation, ObservationId, TrialId};
+use crate::trial::{FailureReason, Observation, ObservationId, TrialId};
+use crate::tuners::constraint::UnsatisfiableConstraint;
 use crate::tuners::{Action, Tune, Tuner};
 use crate::types::Scope;
 use std::io::{BufRead, Write};
+use std::net::SocketAddr;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::{Duration, Instant};
 
 mod command;
+mod dedupe;
+mod extract;
+mod limits;
 mod loader;
+mod slots;
+mod stop;
 mod tempdir;
+mod warm_start;
+
+pub use self::dedupe::DedupeMode;
+pub use self::extract::{MetricMatches, MetricPattern};
+pub use self::limits::ResourceLimits;
+pub use self::slots::WorkerResource;
+pub use self::stop::{MetricThreshold, StopConditions};
+pub use self::warm_start::ParamRename;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct StudyRunnerOpt {
     pub study: StudySpec,
     pub workers: NonZeroUsize,
     pub repeat: Option<usize>,
+    pub kill_grace_period: Duration,
+    #[serde(default)]
+    pub resources: Vec<WorkerResource>,
+    #[serde(default)]
+    pub stop: StopConditions,
+    #[serde(default)]
+    pub dedupe: Option<DedupeMode>,
+}
+
+/// The name of the metric recorded by `ObservationSpec::minimize_elapsed`.
+const ELAPSED_METRIC: &str = "elapsed";
+
+/// The name of the metric recorded by `ObservationSpec::metric_exit_code`.
+const EXIT_CODE_METRIC: &str = "exit_code";
+
+impl StudyRunnerOpt {
+    /// Makes an option that runs `study` with a single worker and no optional features enabled.
+    pub fn new(study: StudySpec) -> Self {
+        Self {
+            study,
+            workers: NonZeroUsize::new(1).expect("unreachable"),
+            repeat: None,
+            kill_grace_period: Duration::from_secs(10),
+            resources: Vec::new(),
+            stop: StopConditions::default(),
+            dedupe: None,
+        }
+    }
+
+    fn auto_metric_names(&self) -> impl Iterator<Item = &'static str> {
+        let spec = &self.study.observation;
+        let elapsed = Some(ELAPSED_METRIC).filter(|_| spec.minimize_elapsed);
+        let exit_code = Some(EXIT_CODE_METRIC).filter(|_| spec.metric_exit_code);
+        elapsed.into_iter().chain(exit_code)
+    }
+}
+
+#[derive(Debug)]
+enum RunnerEvent {
+    Rpc(rpc::Message),
+    Metric {
+        obs_id: ObservationId,
+        name: MetricName,
+        ty: MetricType,
+        value: MetricValue,
+    },
+    Exited {
+        obs_id: ObservationId,
+        exit: Option<self::limits::ExitInfo>,
+        elapsed: Duration,
+    },
+}
+
+impl From<rpc::Message> for RunnerEvent {
+    fn from(f: rpc::Message) -> Self {
+        Self::Rpc(f)
+    }
 }
 
 #[derive(Debug)]
 pub struct StudyRunner<W> {
     output: EventWriter<W>,
     runnings: Vec<CommandRunner>,
+    slots: WorkerSlots,
     next_obs_id: ObservationId,
     next_trial_id: TrialId,
-    rpc_channel: rpc::Channel,
+    rpc_server_addr: SocketAddr,
+    event_tx: Sender<RunnerEvent>,
+    event_rx: Receiver<RunnerEvent>,
     tuner: Tuner,
     opt: StudyRunnerOpt,
     start_time: Instant,
     elapsed_offset: Duration,
     tempdirs: TempDirs,
-    terminating: bool,
+    warm_start: WarmStart,
+    patience: PatienceCounter,
+    dedupe: Dedupe,
+    stop_reason: Option<StopReason>,
+    draining: bool,
+    finished_count: usize,
+    next_memory_check: Option<Instant>,
 }
 
 impl<W: Write> StudyRunner<W> {
     pub fn new(output: W, opt: StudyRunnerOpt) -> anyhow::Result<Self> {
-        let tuner = opt.study.tuner.build()?;
-        let rpc_channel = rpc::spawn_rpc_server()?;
+        if let Some(mode) = opt.dedupe {
+            mode.check_tuner(&opt.study.tuner)?;
+        }
+        let tuner = opt.study.build_tuner()?;
+        Self::with_tuner(output, opt, tuner)
+    }
+
+    /// Makes a runner that uses `tuner` instead of the one specified by `opt.study.tuner`.
+    pub fn with_tuner(output: W, opt: StudyRunnerOpt, tuner: Tuner) -> anyhow::Result<Self> {
+        for pattern in &opt.study.observation.metric_patterns {
+            if opt
+                .auto_metric_names()
+                .any(|name| pattern.name.get() == name)
+            {
+                anyhow::bail!(
+                    "the metric {:?} is already recorded automatically",
+                    pattern.name.get()
+                );
+            }
+        }
+
+        let (event_tx, event_rx) = mpsc::channel();
+        let rpc_server_addr = rpc::spawn_rpc_server(event_tx.clone())?;
 
         let mut output = EventWriter::new(output);
         output.write(Event::study_started())?;
@@ -51,15 +158,24 @@ impl<W: Write> StudyRunner<W> {
         Ok(Self {
             output,
             runnings: Vec::new(),
-            rpc_channel,
+            slots: WorkerSlots::new(opt.workers.get()),
+            rpc_server_addr,
+            event_tx,
+            event_rx,
             next_obs_id: ObservationId::new(0),
             next_trial_id: TrialId::new(0),
             tuner,
-            opt,
             start_time: Instant::now(),
             tempdirs: TempDirs::new(),
             elapsed_offset: Duration::new(0, 0),
-            terminating: false,
+            warm_start: WarmStart::new(),
+            patience: PatienceCounter::default(),
+            dedupe: Dedupe::new(opt.dedupe),
+            stop_reason: None,
+            draining: false,
+            finished_count: 0,
+            next_memory_check: None,
+            opt,
         })
     }
 
@@ -68,66 +184,287 @@ impl<W: Write> StudyRunner<W> {
         loader.load(reader)
     }
 
+    /// Tells the succeeded observations of a prior study to the tuner before anything is asked.
+    pub fn warm_start<R: BufRead>(
+        &mut self,
+        reader: EventReader<R>,
+        renames: &[ParamRename],
+    ) -> anyhow::Result<()> {
+        self.warm_start.load(reader, renames)?;
+        for p in &self.opt.study.command.params {
+            self.warm_start.learn(&p.name, &p.ty);
+        }
+        for obs in self.warm_start.take_ready() {
+            self.tuner.tell(&obs)?;
+        }
+        Ok(())
+    }
+
     // TODO: add signal handling
     pub fn run(mut self) -> anyhow::Result<()> {
         self.start_time = Instant::now();
+        let budget_deadline = self
+            .opt
+            .stop
+            .time_budget
+            .map(|t| self.start_time + t.to_duration());
+        if self.opt.study.observation.limits.memory.is_some() {
+            self.next_memory_check = Some(self.start_time);
+        }
 
-        let mut finished_count = 0;
-        let mut did_nothing;
-        while self.opt.repeat.map_or(true, |n| finished_count < n) {
-            did_nothing = true;
+        let reason = loop {
+            if self.is_repeat_done() {
+                break StopReason::Repeat;
+            }
+            if budget_deadline.is_some_and(|t| t <= Instant::now()) {
+                self.stop(StopReason::TimeBudget, true)?;
+            }
+            if self.next_memory_check.is_some_and(|t| t <= Instant::now()) {
+                self.check_memory_usage()?;
+            }
 
-            while self.runnings.len() < self.opt.workers.get() && !self.terminating {
+            // Observations finished from the cache never become running, so the repeat count is checked too.
+            // While draining, only the actions for the started trials (e.g., resuming them) are handled.
+            while self.runnings.len() < self.opt.workers.get()
+                && (self.stop_reason.is_none() || self.draining)
+                && !self.is_repeat_done()
+            {
                 let action = self.tuner.next_action();
                 let waiting = matches!(action, Some(Action::WaitObservations));
+                if self.stop_reason.is_some() && (waiting || action.is_none()) {
+                    break;
+                }
                 self.handle_action(action)?;
                 if waiting {
                     break;
-                } else {
-                    did_nothing = false;
                 }
             }
+            if self.runnings.is_empty() {
+                if let Some(reason) = self.stop_reason.take() {
+                    break reason;
+                }
+            }
+            anyhow::ensure!(
+                !self.runnings.is_empty() || self.stop_reason.is_some() || self.is_repeat_done(),
+                "the tuner is waiting for observations but there are no running ones"
+            );
+            if self.runnings.is_empty() {
+                continue;
+            }
+
+            let budget_deadline =
+                budget_deadline.filter(|_| self.stop_reason.is_none() || self.draining);
+            let deadline = self
+                .runnings
+                .iter()
+                .filter_map(|o| o.kill_deadline())
+                .chain(budget_deadline)
+                .chain(self.next_memory_check)
+                .min();
+            if let Some(deadline) = deadline {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match self.event_rx.recv_timeout(timeout) {
+                    Ok(event) => self.handle_event(event)?,
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        for worker in &mut self.runnings {
+                            worker.escalate_kill()?;
+                        }
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            } else {
+                let event = self.event_rx.recv()?;
+                self.handle_event(event)?;
+            }
+            while let Ok(event) = self.event_rx.try_recv() {
+                self.handle_event(event)?;
+            }
+        };
+        self.output.write(Event::study_finished(reason))?;
+        Ok(())
+    }
+
+    fn is_repeat_done(&self) -> bool {
+        self.opt.repeat.is_some_and(|n| self.finished_count >= n)
+    }
+
+    /// Stops starting new trials, and kills the running observations if `kill` is `true`.
+    ///
+    /// Otherwise, the started trials are drained (i.e., they can still be resumed).
+    fn stop(&mut self, reason: StopReason, kill: bool) -> anyhow::Result<()> {
+        if self.stop_reason.is_none() {
+            self.stop_reason = Some(reason);
+            self.draining = !kill;
+        }
+        if kill {
+            self.draining = false;
+            for worker in &mut self.runnings {
+                worker.kill(self.opt.kill_grace_period)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_memory_usage(&mut self) -> anyhow::Result<()> {
+        if let Some(limit) = self.opt.study.observation.limits.memory {
+            let usages = self::limits::rss_by_process_group()?;
+            for worker in &mut self.runnings {
+                if usages
+                    .get(&worker.process_group_id())
+                    .is_some_and(|&usage| usage > limit)
+                {
+                    worker.kill_by_memory_limit()?;
+                }
+            }
+            self.next_memory_check = Some(Instant::now() + self::limits::MEMORY_CHECK_INTERVAL);
+        }
+        Ok(())
+    }
 
-            while let Some(message) = self.rpc_channel.try_recv() {
+    fn handle_event(&mut self, event: RunnerEvent) -> anyhow::Result<()> {
+        match event {
+            RunnerEvent::Rpc(message) => {
                 self.handle_message(message)?;
-                did_nothing = false;
-            }
-
-            let mut i = 0;
-            while i < self.runnings.len() {
-                if self.runnings[i].is_exited()? {
-                    finished_count += 1;
-                    let obs = self.runnings.swap_remove(i).into_obs();
-                    self.tell_finished_obs(obs, self.start_time.elapsed())?;
-                    did_nothing = false;
-                } else {
-                    i += 1;
+            }
+            RunnerEvent::Metric {
+                obs_id,
+                name,
+                ty,
+                value,
+            } => {
+                if self.opt.study.observation.metric_matches == MetricMatches::All {
+                    self.insert_intermediate_metric(obs_id, name.clone(), value)?;
+                }
+                self.insert_metric(obs_id, name, ty, value)?;
+            }
+            RunnerEvent::Exited {
+                obs_id,
+                exit,
+                elapsed,
+            } => {
+                let i = self
+                    .runnings
+                    .iter()
+                    .position(|o| o.obs().id == obs_id)
+                    .ok_or_else(|| anyhow::anyhow!("unknown observation_id {}", obs_id.get()))?;
+                let mut worker = self.runnings.swap_remove(i);
+                worker.set_exited(exit);
+                self.slots.release(worker.worker_id());
+                self.insert_auto_metrics(&mut worker, elapsed)?;
+                let killed = worker.is_killed();
+                let mut obs = worker.into_obs();
+
+                // The parameters of a killed observation may be incomplete.
+                if !killed {
+                    self.dedupe.mark_duplicate(&mut obs);
                 }
+                self.finished_count += 1;
+                self.tell_finished_obs(obs, self.start_time.elapsed(), false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts the metrics recorded automatically, which take precedence over the reported ones.
+    fn insert_auto_metrics(
+        &self,
+        worker: &mut CommandRunner,
+        elapsed: Duration,
+    ) -> anyhow::Result<()> {
+        let mut metrics = Vec::new();
+        if self.opt.study.observation.minimize_elapsed {
+            let value = MetricValue::new(elapsed.as_secs_f64())?;
+            metrics.push((ELAPSED_METRIC, MetricType::Minimize, value));
+        }
+        if self.opt.study.observation.metric_exit_code {
+            if let Some(code) = worker.obs().exit_status {
+                let value = MetricValue::new(f64::from(code))?;
+                metrics.push((EXIT_CODE_METRIC, MetricType::Record, value));
             }
+        }
 
-            if did_nothing {
-                std::thread::sleep(Duration::from_millis(1));
+        let obs = worker.obs_mut();
+        for (name, ty, value) in metrics {
+            let name = MetricName::new(name.to_owned());
+            if obs.metrics.contains_key(&name) {
+                eprintln!(
+                    "warning: the metric {:?} reported by the observation {} is overwritten by the automatically recorded one",
+                    name.get(),
+                    obs.id.get()
+                );
             }
+            obs.metrics.insert(name, MetricInstance::new(ty, value));
         }
         Ok(())
     }
 
-    fn tell_finished_obs(&mut self, obs: Observation, elapsed: Duration) -> anyhow::Result<()> {
+    /// Tells a finished observation to the tuner and records it.
+    ///
+    /// The stop conditions aren't checked for observations `replaying` a loaded study.
+    fn tell_finished_obs(
+        &mut self,
+        obs: Observation,
+        elapsed: Duration,
+        replaying: bool,
+    ) -> anyhow::Result<()> {
+        for (name, p) in &obs.params {
+            self.warm_start.learn(name, &p.ty);
+        }
         self.tuner.tell(&obs)?;
+        self.dedupe.insert(&obs);
+        if !replaying {
+            self.check_stop_conditions(&obs)?;
+        }
         self.finish_obs(obs, elapsed)?;
         Ok(())
     }
 
-    fn start_obs(&mut self, obs: Observation) -> anyhow::Result<()> {
+    fn check_stop_conditions(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        if !obs.is_succeeded() {
+            return Ok(());
+        }
+
+        let stop = &self.opt.stop;
+        if let Some(condition) = stop.stop_when.iter().find(|c| c.is_satisfied(obs)) {
+            let condition = condition.to_string();
+            self.stop(StopReason::TargetReached { condition }, true)?;
+        }
+        let count = self.patience.update(obs);
+        if self.opt.stop.patience.is_some_and(|n| count >= n) {
+            self.stop(StopReason::Patience, false)?;
+        }
+        Ok(())
+    }
+
+    fn start_obs(&mut self, mut obs: Observation) -> anyhow::Result<()> {
         self.output.write(Event::observation_started(
             obs.id,
             obs.trial_id,
             self.elapsed_offset + self.start_time.elapsed(),
         ))?;
+        for p in &self.opt.study.command.params {
+            let value = self.dedupe.ask(&mut self.tuner, &obs, &p.name, &p.ty)?;
+            obs.insert_param(p.name.clone(), ParamInstance::new(p.ty.clone(), value));
+        }
+        if let Some(cached) = self.dedupe.find_cached(&obs) {
+            self::dedupe::apply_cached(&mut obs, cached);
+            self.finished_count += 1;
+            if self.dedupe.record_cache_hit(true) {
+                self.stop(StopReason::Exhausted, false)?;
+            }
+            return self.tell_finished_obs(obs, self.start_time.elapsed(), false);
+        }
+        self.dedupe.record_cache_hit(false);
+        let worker_id = self
+            .slots
+            .acquire()
+            .ok_or_else(|| anyhow::anyhow!("no free worker slot"))?;
         self.runnings.push(CommandRunner::spawn(
-            &self.opt.study,
+            &self.opt,
             obs,
-            self.rpc_channel.server_addr,
+            self.rpc_server_addr,
+            worker_id,
+            self.event_tx.clone(),
         )?);
         Ok(())
     }
@@ -152,12 +489,28 @@ impl<W: Write> StudyRunner<W> {
     }
 
     fn handle_action(&mut self, action: Option<Action>) -> anyhow::Result<()> {
+        if action
+            .as_ref()
+            .is_some_and(|a| self.warm_start.is_warm_action(a))
+        {
+            return Ok(());
+        }
+
         match action {
             None => {
+                if self
+                    .opt
+                    .stop
+                    .max_trials
+                    .is_some_and(|n| self.next_trial_id.get() >= n as u64)
+                {
+                    return self.stop(StopReason::MaxTrials, false);
+                }
                 let obs = Observation::new(
                     self.next_obs_id.fetch_and_increment(),
                     self.next_trial_id.fetch_and_increment(),
                 );
+                self.tuner.start_trial(obs.trial_id);
                 self.start_trial(obs.trial_id)?;
                 self.start_obs(obs)?;
             }
@@ -168,12 +521,17 @@ impl<W: Write> StudyRunner<W> {
             Some(Action::FinishTrial { trial_id }) => {
                 self.finish_trial(trial_id)?;
             }
+            Some(Action::CopyTrialDir { from, to }) => {
+                anyhow::ensure!(
+                    self.runnings.iter().all(|o| o.obs().trial_id != from),
+                    "cannot copy the directory of the running trial {}",
+                    from.get()
+                );
+                self.tempdirs.copy_trial_tempdir(from, to)?;
+            }
             Some(Action::WaitObservations) => {}
             Some(Action::QuitOptimization) => {
-                self.terminating = true;
-                for worker in &mut self.runnings {
-                    worker.kill()?;
-                }
+                self.stop(StopReason::TunerFinished, true)?;
             }
         }
         Ok(())
@@ -182,8 +540,29 @@ impl<W: Write> StudyRunner<W> {
     fn handle_message(&mut self, message: rpc::Message) -> anyhow::Result<()> {
         match message {
             rpc::Message::Ask { req, reply } => {
-                let value = self.handle_ask(req)?;
-                reply.send(value)?;
+                let obs_id = req.observation_id;
+                match self.handle_ask(req) {
+                    Ok(value) => reply.send(value)?,
+                    Err(e) => self.fail_unsatisfiable_obs(obs_id, e)?,
+                }
+            }
+            rpc::Message::AskMany { req, reply } => {
+                let obs_id = req.observation_id;
+                let values = req
+                    .params
+                    .into_iter()
+                    .map(|(param_name, param_type)| {
+                        self.handle_ask(rpc::AskReq {
+                            observation_id: obs_id,
+                            param_name,
+                            param_type,
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>();
+                match values {
+                    Ok(values) => reply.send(values)?,
+                    Err(e) => self.fail_unsatisfiable_obs(obs_id, e)?,
+                }
             }
             rpc::Message::Tell { req, reply } => {
                 self.handle_tell(req)?;
@@ -219,18 +598,42 @@ impl<W: Write> StudyRunner<W> {
         }
     }
 
+    /// Kills the observation if `e` is an `UnsatisfiableConstraint` error, or returns `e` otherwise.
+    ///
+    /// The reply to the ask is dropped, so the process waits until it's killed.
+    fn fail_unsatisfiable_obs(
+        &mut self,
+        obs_id: ObservationId,
+        e: anyhow::Error,
+    ) -> anyhow::Result<()> {
+        if !e.is::<UnsatisfiableConstraint>() {
+            return Err(e);
+        }
+        eprintln!("warning: the observation {} is killed: {}", obs_id.get(), e);
+        let grace_period = self.opt.kill_grace_period;
+        self.runnings
+            .iter_mut()
+            .find(|o| o.obs().id == obs_id)
+            .ok_or_else(|| anyhow::anyhow!("unknown observation_id {}", obs_id.get()))?
+            .kill_by_failure(FailureReason::ConstraintUnsatisfied, grace_period)
+    }
+
     fn handle_ask(&mut self, req: rpc::AskReq) -> anyhow::Result<ParamValue> {
-        let obs = self
+        let worker = self
             .runnings
             .iter_mut()
             .find(|o| o.obs().id == req.observation_id)
-            .ok_or_else(|| anyhow::anyhow!("unknown observation_id {}", req.observation_id.get()))?
-            .obs_mut();
+            .ok_or_else(|| {
+                anyhow::anyhow!("unknown observation_id {}", req.observation_id.get())
+            })?;
+        let obs = worker.obs_mut();
         if let Some(instance) = obs.params.get(&req.param_name) {
             Ok(instance.value.clone())
         } else {
-            let value = self.tuner.ask(obs, &req.param_name, &req.param_type)?;
-            obs.params.insert(
+            let value = self
+                .dedupe
+                .ask(&mut self.tuner, obs, &req.param_name, &req.param_type)?;
+            obs.insert_param(
                 req.param_name,
                 ParamInstance::new(req.param_type, value.clone()),
             );
@@ -239,16 +642,47 @@ impl<W: Write> StudyRunner<W> {
     }
 
     fn handle_tell(&mut self, req: rpc::TellReq) -> anyhow::Result<()> {
+        self.insert_metric(
+            req.observation_id,
+            req.metric_name,
+            req.metric_type,
+            req.metric_value,
+        )
+    }
+
+    fn insert_metric(
+        &mut self,
+        obs_id: ObservationId,
+        name: MetricName,
+        ty: MetricType,
+        value: MetricValue,
+    ) -> anyhow::Result<()> {
         let obs = self
             .runnings
             .iter_mut()
-            .find(|o| o.obs().id == req.observation_id)
-            .ok_or_else(|| anyhow::anyhow!("unknown observation_id {}", req.observation_id.get()))?
+            .find(|o| o.obs().id == obs_id)
+            .ok_or_else(|| anyhow::anyhow!("unknown observation_id {}", obs_id.get()))?
             .obs_mut();
-        obs.metrics.insert(
-            req.metric_name,
-            MetricInstance::new(req.metric_type, req.metric_value),
-        );
+        obs.metrics.insert(name, MetricInstance::new(ty, value));
+        Ok(())
+    }
+
+    fn insert_intermediate_metric(
+        &mut self,
+        obs_id: ObservationId,
+        name: MetricName,
+        value: MetricValue,
+    ) -> anyhow::Result<()> {
+        let obs = self
+            .runnings
+            .iter_mut()
+            .find(|o| o.obs().id == obs_id)
+            .ok_or_else(|| anyhow::anyhow!("unknown observation_id {}", obs_id.get()))?
+            .obs_mut();
+        obs.intermediate_metrics
+            .entry(name)
+            .or_default()
+            .push(value);
         Ok(())
     }
 }
@@ -1,43 +1,175 @@
+use super::extract::{spawn_extractor, MetricChannel};
+use super::limits::{ExitInfo, ResourceLimits};
+use super::{RunnerEvent, StudyRunnerOpt};
 use crate::envvar;
-use crate::study::StudySpec;
-use crate::trial::Observation;
+use crate::trial::{FailureReason, Observation};
 use anyhow::Context;
 use std::os::unix::io::{AsRawFd, FromRawFd};
-use std::process::{Child, Command, Stdio};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The maximum time to wait for the output of an exited observation to be closed.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The state of a process shared with the thread waiting for it.
+#[derive(Debug, Default)]
+struct ProcessState {
+    /// Once the process is reaped, its PID (and process group ID) may be reused by an unrelated process.
+    reaped: bool,
+
+    /// Whether the remaining processes in the group are killed when the process exits.
+    kill_group_on_exit: bool,
+}
 
 #[derive(Debug)]
 pub struct CommandRunner {
     obs: Observation,
-    proc: Child,
+
+    // The process is spawned as the leader of a new session (and process group),
+    // so the process group ID equals to this.
+    pid: libc::pid_t,
+    state: Arc<Mutex<ProcessState>>,
+
+    worker_id: usize,
+    limits: ResourceLimits,
+    killed: bool,
+    kill_deadline: Option<Instant>,
+    failure: Option<FailureReason>,
+    exited: bool,
 }
 
 impl CommandRunner {
     pub fn spawn(
-        study: &StudySpec,
+        opt: &StudyRunnerOpt,
         obs: Observation,
         rpc_server_addr: std::net::SocketAddr,
+        worker_id: usize,
+        event_tx: Sender<RunnerEvent>,
     ) -> anyhow::Result<Self> {
+        let study = &opt.study;
         let mut command = Command::new(&study.command.path);
 
-        let stdout = unsafe {
-            let fd = libc::dup(std::io::stderr().as_raw_fd());
-            if fd == -1 {
-                Err(std::io::Error::last_os_error())?;
+        // If metrics are extracted from the output, both stdout and stderr are captured.
+        let capture = !opt.study.observation.metric_patterns.is_empty();
+        let stdout = if capture {
+            command.stderr(Stdio::piped());
+            Stdio::piped()
+        } else {
+            unsafe {
+                let fd = libc::dup(std::io::stderr().as_raw_fd());
+                if fd == -1 {
+                    Err(std::io::Error::last_os_error())?;
+                }
+                Stdio::from_raw_fd(fd)
             }
-            Stdio::from_raw_fd(fd)
         };
         command
-            .args(&study.command.args)
+            .args(study.command.render_args(&obs.params))
             .env(envvar::KEY_SERVER_ADDR, rpc_server_addr.to_string())
             .env(envvar::KEY_STUDY_ID, study.id.to_string())
             .env(envvar::KEY_TRIAL_ID, obs.trial_id.get().to_string())
             .env(envvar::KEY_OBSERVATION_ID, obs.id.get().to_string())
+            .env(envvar::KEY_WORKER_ID, worker_id.to_string())
             .stdout(stdout)
             .stdin(Stdio::null());
-        let proc = command
+        for resource in &opt.resources {
+            command.env(&resource.name, resource.get(worker_id));
+        }
+        for p in &study.command.params {
+            if let Some(instance) = obs.params.get(&p.name) {
+                command.env(envvar::param_key(&p.name), instance.value.to_string());
+            }
+        }
+
+        let parent_pid = unsafe { libc::getpid() };
+        let child_limits = opt.study.observation.limits.clone();
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                // The parent-death signal is only delivered to this process, not to its descendants,
+                // so grandchildren survive if hone is killed without a chance to kill the group.
+                #[cfg(target_os = "linux")]
+                {
+                    if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    if libc::getppid() != parent_pid {
+                        // The parent has already died.
+                        libc::_exit(1);
+                    }
+                }
+                #[cfg(not(target_os = "linux"))]
+                let _ = parent_pid;
+
+                child_limits.apply()
+            });
+        }
+        let start_time = Instant::now();
+        let mut proc = command
             .spawn()
             .with_context(|| format!("Failed to spawn command: {:?}", study.command.path))?;
-        Ok(CommandRunner { obs, proc })
+        let pid = proc.id() as libc::pid_t;
+
+        let channel = MetricChannel::new(obs.id, event_tx);
+        let (done_tx, done_rx) = mpsc::channel();
+        if let Some(output) = proc.stdout.take() {
+            let patterns = opt.study.observation.metric_patterns.clone();
+            spawn_extractor(output, patterns, channel.clone(), done_tx.clone())?;
+        }
+        if let Some(output) = proc.stderr.take() {
+            let patterns = opt.study.observation.metric_patterns.clone();
+            spawn_extractor(output, patterns, channel.clone(), done_tx.clone())?;
+        }
+        drop(done_tx);
+
+        // Waits for the process in a dedicated thread so that the runner can block on `event_tx`.
+        // `wait4(2)` is used instead of `Child::wait` to get the resource usage of the process.
+        //
+        // The process is reaped while holding the lock of `state`, so that the runner never signals
+        // a reused PID, and the group is killed before reaping while the zombie still reserves the PID.
+        let obs_id = obs.id;
+        let state = Arc::new(Mutex::new(ProcessState::default()));
+        let waiter_state = Arc::clone(&state);
+        std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || {
+                let _proc = proc;
+                let exited = wait_exited(pid);
+                let mut state = waiter_state.lock().unwrap_or_else(|e| panic!("{}", e));
+                if exited.is_ok() && state.kill_group_on_exit {
+                    unsafe { libc::killpg(pid, libc::SIGKILL) };
+                }
+                let exit = exited.and_then(|()| ExitInfo::wait(pid)).ok();
+                let elapsed = start_time.elapsed();
+                state.reaped = true;
+                drop(state);
+
+                // Waits for the extractors to read the rest of the output so that all the metrics
+                // are sent before `Exited`, unless a remaining process keeps the output open.
+                let _ = done_rx.recv_timeout(OUTPUT_DRAIN_TIMEOUT);
+                channel.close_with(RunnerEvent::Exited {
+                    obs_id,
+                    exit,
+                    elapsed,
+                });
+            })?;
+        Ok(CommandRunner {
+            obs,
+            pid,
+            state,
+            worker_id,
+            limits: opt.study.observation.limits.clone(),
+            killed: false,
+            kill_deadline: None,
+            failure: None,
+            exited: false,
+        })
     }
 
     pub fn obs(&self) -> &Observation {
@@ -48,21 +180,119 @@ impl CommandRunner {
         &mut self.obs
     }
 
-    pub fn into_obs(self) -> Observation {
-        self.obs
+    pub fn into_obs(mut self) -> Observation {
+        let empty = Observation::new(self.obs.id, self.obs.trial_id);
+        std::mem::replace(&mut self.obs, empty)
     }
 
-    pub fn is_exited(&mut self) -> anyhow::Result<bool> {
-        if let Some(exit_status) = self.proc.try_wait()? {
-            self.obs.exit_status = exit_status.code();
-            Ok(true)
-        } else {
-            Ok(false)
+    pub fn worker_id(&self) -> usize {
+        self.worker_id
+    }
+
+    /// Returns `true` if the observation has been killed by the runner.
+    pub fn is_killed(&self) -> bool {
+        self.killed
+    }
+
+    pub fn kill_deadline(&self) -> Option<Instant> {
+        self.kill_deadline
+    }
+
+    /// Returns the ID of the process group of the observation.
+    pub fn process_group_id(&self) -> libc::pid_t {
+        self.pid
+    }
+
+    /// Kills the process group immediately because it has exceeded the memory limit.
+    pub fn kill_by_memory_limit(&mut self) -> anyhow::Result<()> {
+        self.failure = Some(FailureReason::MemoryLimitExceeded);
+        self.killed = true;
+        self.kill_deadline = None;
+        self.lock_state().kill_group_on_exit = true;
+        self.signal(libc::SIGKILL)
+    }
+
+    /// Kills the process group like `kill`, recording `failure` as the reason.
+    pub fn kill_by_failure(
+        &mut self,
+        failure: FailureReason,
+        grace_period: Duration,
+    ) -> anyhow::Result<()> {
+        self.failure = Some(failure);
+        self.kill(grace_period)
+    }
+
+    /// Sends `SIGTERM` to the process group, and `SIGKILL` if it is still alive after `grace_period`.
+    pub fn kill(&mut self, grace_period: Duration) -> anyhow::Result<()> {
+        if !self.killed {
+            self.killed = true;
+            self.kill_deadline = Some(Instant::now() + grace_period);
+            self.lock_state().kill_group_on_exit = true;
+            self.signal(libc::SIGTERM)?;
         }
+        self.escalate_kill()
     }
 
-    pub fn kill(&mut self) -> anyhow::Result<()> {
-        self.proc.kill()?;
+    pub fn escalate_kill(&mut self) -> anyhow::Result<()> {
+        if self.kill_deadline.is_some_and(|t| t <= Instant::now()) {
+            self.kill_deadline = None;
+            self.signal(libc::SIGKILL)?;
+        }
         Ok(())
     }
+
+    /// Marks the process as exited.
+    ///
+    /// If the process has been killed, the remaining processes in the group have been killed too.
+    pub fn set_exited(&mut self, exit: Option<ExitInfo>) {
+        if let Some(exit) = exit {
+            self.obs.exit_status = exit.code;
+            self.obs.failure = self.limits.failure_reason(&exit);
+        }
+        if self.failure.is_some() {
+            self.obs.failure = self.failure;
+        }
+        self.exited = true;
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, ProcessState> {
+        self.state.lock().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn signal(&self, signal: libc::c_int) -> anyhow::Result<()> {
+        let state = self.lock_state();
+        if state.reaped {
+            return Ok(());
+        }
+        if unsafe { libc::killpg(self.pid, signal) } == -1 {
+            let e = std::io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                Err(e)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CommandRunner {
+    fn drop(&mut self) {
+        if !self.exited {
+            let _ = self.signal(libc::SIGKILL);
+        }
+    }
+}
+
+/// Waits for the process to exit without reaping it.
+fn wait_exited(pid: libc::pid_t) -> std::io::Result<()> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    loop {
+        let flags = libc::WEXITED | libc::WNOWAIT;
+        if unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, flags) } != -1 {
+            return Ok(());
+        }
+        let e = std::io::Error::last_os_error();
+        if e.kind() != std::io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
 }
@@ -0,0 +1,321 @@
+use crate::param::{ParamName, ParamType, ParamValue};
+use crate::trial::Observation;
+use crate::tuners::{Tune, Tuner, TunerSpec};
+use std::collections::{BTreeMap, HashMap};
+
+/// The maximum number of values asked to the tuner for a parameter to avoid a duplicate configuration.
+const MAX_RESAMPLE_ATTEMPTS: usize = 100;
+
+/// The number of consecutive observations finished from the cache after which the space is regarded as exhausted.
+const MAX_CONSECUTIVE_CACHE_HITS: usize = 1000;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupeMode {
+    /// Asks the tuner again for the last parameter completing a duplicate configuration.
+    Resample,
+
+    /// Finishes a duplicate observation with the metrics of the already-finished one.
+    Cache,
+}
+
+impl DedupeMode {
+    /// Ensures that `tuner` can be used with this mode.
+    ///
+    /// Deterministic tuners propose the same value again, so they can't resample a duplicate.
+    pub fn check_tuner(self, tuner: &TunerSpec) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !(self == Self::Resample && tuner.is_deterministic()),
+            "`--dedupe resample` can't be used with deterministic tuners (`sobol`, `halton`, `lhs` or `pbt`), \
+             which can't resample values"
+        );
+        Ok(())
+    }
+}
+
+/// Succeeded observations keyed by their parameters, used to detect duplicate configurations.
+#[derive(Debug)]
+pub struct Dedupe {
+    mode: Option<DedupeMode>,
+    finished: HashMap<BTreeMap<ParamName, ParamValue>, Observation>,
+    consecutive_cache_hits: usize,
+}
+
+impl Dedupe {
+    pub fn new(mode: Option<DedupeMode>) -> Self {
+        Self {
+            mode,
+            finished: HashMap::new(),
+            consecutive_cache_hits: 0,
+        }
+    }
+
+    pub fn insert(&mut self, obs: &Observation) {
+        if self.mode.is_some() && obs.is_succeeded() {
+            self.finished
+                .entry(obs.to_compact().params)
+                .or_insert_with(|| obs.clone());
+        }
+    }
+
+    /// Asks the tuner for a value, which is resampled if it completes an already-finished configuration.
+    ///
+    /// If every attempt ends up with a duplicate, the last value is returned as-is.
+    pub fn ask(
+        &self,
+        tuner: &mut Tuner,
+        obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        let mut value = tuner.ask(obs, param_name, param_type)?;
+        if self.mode != Some(DedupeMode::Resample) {
+            return Ok(value);
+        }
+
+        let mut params = obs.to_compact().params;
+        for _ in 1..MAX_RESAMPLE_ATTEMPTS {
+            params.insert(param_name.clone(), value.clone());
+            if !self.finished.contains_key(&params) {
+                break;
+            }
+            value = tuner.ask(obs, param_name, param_type)?;
+        }
+        Ok(value)
+    }
+
+    /// Returns the already-finished observation that has the same parameters as `obs` in the cache mode.
+    ///
+    /// The parameters of `obs` must be complete (i.e., no more parameters are asked),
+    /// otherwise a prefix of a different configuration may be regarded as a duplicate.
+    pub fn find_cached(&self, obs: &Observation) -> Option<&Observation> {
+        if self.mode != Some(DedupeMode::Cache) {
+            return None;
+        }
+        self.finished.get(&obs.to_compact().params)
+    }
+
+    /// Sets `duplicate_of` of an exited observation if an already-finished one has the same parameters.
+    ///
+    /// The result of `obs` is kept as it is, since the command has already run.
+    pub fn mark_duplicate(&self, obs: &mut Observation) {
+        if self.mode.is_some() {
+            obs.duplicate_of = self.finished.get(&obs.to_compact().params).map(|o| o.id);
+        }
+    }
+
+    /// Records whether a started observation was finished from the cache (`hit`) or spawned.
+    ///
+    /// Returns `true` if the last `MAX_CONSECUTIVE_CACHE_HITS` observations were all finished from the cache,
+    /// which means that the tuner keeps proposing only the already-finished configurations.
+    pub fn record_cache_hit(&mut self, hit: bool) -> bool {
+        if hit {
+            self.consecutive_cache_hits += 1;
+        } else {
+            self.consecutive_cache_hits = 0;
+        }
+        self.consecutive_cache_hits >= MAX_CONSECUTIVE_CACHE_HITS
+    }
+}
+
+/// Makes `obs` a copy of the result of `cached`.
+pub fn apply_cached(obs: &mut Observation, cached: &Observation) {
+    obs.metrics = cached.metrics.clone();
+    obs.intermediate_metrics = cached.intermediate_metrics.clone();
+    obs.exit_status = cached.exit_status;
+    obs.failure = cached.failure;
+    obs.cached = Some(cached.id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{MetricInstance, MetricName, MetricType, MetricValue};
+    use crate::param::{DiscreteParamType, NumParamType, ParamInstance};
+    use crate::trial::{ObservationId, TrialId};
+    use crate::tuners::random::RandomTunerSpec;
+    use crate::types::FiniteF64;
+
+    fn num(v: f64) -> ParamValue {
+        ParamValue::Num(FiniteF64::new(v).expect("finite"))
+    }
+
+    fn discrete(max: f64) -> anyhow::Result<ParamType> {
+        let ty = DiscreteParamType::new(0.0, max, 1.0, false, None)?;
+        Ok(ParamType::Num(NumParamType::Discrete(ty)))
+    }
+
+    fn random_tuner() -> anyhow::Result<Tuner> {
+        let spec = RandomTunerSpec {
+            seed: Some("0".parse()?),
+        };
+        Ok(Tuner::new(spec.build()?))
+    }
+
+    fn finished_obs(id: u64, params: &[(&str, f64)], loss: f64) -> anyhow::Result<Observation> {
+        let mut obs = Observation::new(ObservationId::new(id), TrialId::new(id));
+        for (name, v) in params {
+            let instance = ParamInstance::new(discrete(3.0)?, num(*v));
+            obs.insert_param(ParamName::new((*name).to_owned()), instance);
+        }
+        let loss = MetricInstance::new(MetricType::Minimize, MetricValue::new(loss)?);
+        obs.metrics.insert(MetricName::new("loss".to_owned()), loss);
+        obs.exit_status = Some(0);
+        Ok(obs)
+    }
+
+    #[test]
+    fn resample_rejects_deterministic_tuners() -> anyhow::Result<()> {
+        let lhs: TunerSpec = r#"{"lhs":{"samples":10}}"#.parse()?;
+
+        // LHS proposes the same stratum, i.e., the same value, when asked again for the observation.
+        let mut tuner = lhs.build()?;
+        let x = ParamName::new("x".to_owned());
+        let obs = Observation::new(ObservationId::new(0), TrialId::new(0));
+        let value = tuner.ask(&obs, &x, &discrete(9.0)?)?;
+        assert_eq!(tuner.ask(&obs, &x, &discrete(9.0)?)?, value);
+
+        assert!(DedupeMode::Resample.check_tuner(&lhs).is_err());
+        assert!(DedupeMode::Cache.check_tuner(&lhs).is_ok());
+        assert!(DedupeMode::Resample
+            .check_tuner(&TunerSpec::default())
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn resample_avoids_finished_configurations() -> anyhow::Result<()> {
+        let mut dedupe = Dedupe::new(Some(DedupeMode::Resample));
+        for v in 0..3 {
+            dedupe.insert(&finished_obs(v, &[("x", v as f64)], 0.0)?);
+        }
+
+        let mut tuner = random_tuner()?;
+        let x = ParamName::new("x".to_owned());
+        let obs = Observation::new(ObservationId::new(10), TrialId::new(10));
+        for _ in 0..100 {
+            assert_eq!(dedupe.ask(&mut tuner, &obs, &x, &discrete(3.0)?)?, num(3.0));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn resample_compares_the_whole_configuration() -> anyhow::Result<()> {
+        let mut dedupe = Dedupe::new(Some(DedupeMode::Resample));
+        dedupe.insert(&finished_obs(0, &[("x", 0.0), ("y", 0.0)], 0.0)?);
+
+        // `x=0` alone isn't a duplicate, but `y=0` completes one after `x=0`.
+        let mut tuner = random_tuner()?;
+        let (x, y) = (
+            ParamName::new("x".to_owned()),
+            ParamName::new("y".to_owned()),
+        );
+        let mut obs = Observation::new(ObservationId::new(10), TrialId::new(10));
+        obs.insert_param(x.clone(), ParamInstance::new(discrete(1.0)?, num(0.0)));
+        for _ in 0..100 {
+            assert_eq!(dedupe.ask(&mut tuner, &obs, &y, &discrete(1.0)?)?, num(1.0));
+        }
+
+        let mut values = Vec::new();
+        let obs = Observation::new(ObservationId::new(11), TrialId::new(11));
+        for _ in 0..100 {
+            values.push(dedupe.ask(&mut tuner, &obs, &x, &discrete(1.0)?)?);
+        }
+        assert!(values.contains(&num(0.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn resample_returns_a_duplicate_if_the_space_is_exhausted() -> anyhow::Result<()> {
+        let mut dedupe = Dedupe::new(Some(DedupeMode::Resample));
+        for v in 0..2 {
+            dedupe.insert(&finished_obs(v, &[("x", v as f64)], 0.0)?);
+        }
+
+        let mut tuner = random_tuner()?;
+        let x = ParamName::new("x".to_owned());
+        let obs = Observation::new(ObservationId::new(10), TrialId::new(10));
+        let value = dedupe.ask(&mut tuner, &obs, &x, &discrete(1.0)?)?;
+        assert!(value == num(0.0) || value == num(1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn cache_finds_only_the_same_succeeded_configuration() -> anyhow::Result<()> {
+        let mut dedupe = Dedupe::new(Some(DedupeMode::Cache));
+        dedupe.insert(&finished_obs(0, &[("x", 0.0), ("y", 1.0)], 0.5)?);
+        let mut failed = finished_obs(1, &[("x", 1.0), ("y", 1.0)], 0.0)?;
+        failed.exit_status = Some(1);
+        dedupe.insert(&failed);
+
+        let cached = dedupe.find_cached(&finished_obs(2, &[("x", 0.0), ("y", 1.0)], 0.0)?);
+        assert_eq!(cached.map(|o| o.id), Some(ObservationId::new(0)));
+
+        // A prefix or an extension of a finished configuration isn't a duplicate.
+        assert!(dedupe
+            .find_cached(&finished_obs(3, &[("x", 0.0)], 0.0)?)
+            .is_none());
+        let extended = finished_obs(4, &[("x", 0.0), ("y", 1.0), ("z", 2.0)], 0.0)?;
+        assert!(dedupe.find_cached(&extended).is_none());
+        assert!(dedupe
+            .find_cached(&finished_obs(5, &[("x", 1.0), ("y", 1.0)], 0.0)?)
+            .is_none());
+
+        let resample = Dedupe::new(Some(DedupeMode::Resample));
+        assert!(resample.find_cached(&finished_obs(6, &[], 0.0)?).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn cached_result_is_applied_to_a_new_observation() -> anyhow::Result<()> {
+        let cached = finished_obs(0, &[("x", 0.0)], 0.5)?;
+        let mut obs = Observation::new(ObservationId::new(1), TrialId::new(1));
+        obs.insert_param(
+            ParamName::new("x".to_owned()),
+            ParamInstance::new(discrete(3.0)?, num(0.0)),
+        );
+        apply_cached(&mut obs, &cached);
+
+        assert_eq!(obs.id, ObservationId::new(1));
+        assert_eq!(obs.cached, Some(cached.id));
+        assert_eq!(obs.exit_status, Some(0));
+        let loss = &obs.metrics[&MetricName::new("loss".to_owned())];
+        assert_eq!(loss.value.get(), 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn exited_duplicate_keeps_its_own_result() -> anyhow::Result<()> {
+        let mut dedupe = Dedupe::new(Some(DedupeMode::Cache));
+        dedupe.insert(&finished_obs(0, &[("x", 0.0)], 0.5)?);
+
+        let mut obs = finished_obs(1, &[("x", 0.0)], 0.25)?;
+        obs.exit_status = Some(1);
+        dedupe.mark_duplicate(&mut obs);
+        assert_eq!(obs.duplicate_of, Some(ObservationId::new(0)));
+        assert_eq!(obs.cached, None);
+        assert_eq!(obs.exit_status, Some(1));
+        let loss = &obs.metrics[&MetricName::new("loss".to_owned())];
+        assert_eq!(loss.value.get(), 0.25);
+
+        let mut obs = finished_obs(2, &[("x", 1.0)], 0.25)?;
+        dedupe.mark_duplicate(&mut obs);
+        assert_eq!(obs.duplicate_of, None);
+        Ok(())
+    }
+
+    #[test]
+    fn consecutive_cache_hits_exhaust_the_space() {
+        let mut dedupe = Dedupe::new(Some(DedupeMode::Cache));
+        for _ in 1..MAX_CONSECUTIVE_CACHE_HITS {
+            assert!(!dedupe.record_cache_hit(true));
+        }
+        assert!(!dedupe.record_cache_hit(false));
+        for _ in 1..MAX_CONSECUTIVE_CACHE_HITS {
+            assert!(!dedupe.record_cache_hit(true));
+        }
+        assert!(dedupe.record_cache_hit(true));
+    }
+}
@@ -0,0 +1,175 @@
+use super::RunnerEvent;
+use crate::metric::{MetricName, MetricType, MetricValue};
+use crate::trial::ObservationId;
+use regex::Regex;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// A pattern to extract a metric from each line of the output of an observation,
+/// written as `TYPE:NAME=REGEX` (e.g., `minimize:loss=Test set: Average loss: ([0-9.]+)`).
+///
+/// The first capture group (or the whole match if there is no group) is parsed as the metric value.
+/// Every match is told in the order it appears, so the last one wins
+/// (see `MetricMatches` for recording the other ones).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MetricPattern {
+    pub ty: MetricType,
+    pub name: MetricName,
+    pub regex: Regex,
+}
+
+impl MetricPattern {
+    fn extract(&self, line: &str) -> Option<MetricValue> {
+        let captures = self.regex.captures(line)?;
+        let m = captures.get(1).or_else(|| captures.get(0))?;
+        MetricValue::new(m.as_str().trim().parse().ok()?).ok()
+    }
+}
+
+/// How the matches of a `MetricPattern` other than the last one are handled.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricMatches {
+    /// Only the last match is recorded as the metric value.
+    #[default]
+    Last,
+
+    /// Every match is also recorded as an intermediate value of the metric.
+    All,
+}
+
+impl std::str::FromStr for MetricPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ty, rest) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("No metric type part in a metric pattern: {:?}", s))?;
+        let ty = match ty {
+            "minimize" => MetricType::Minimize,
+            "maximize" => MetricType::Maximize,
+            "record" => MetricType::Record,
+            _ => anyhow::bail!("Unknown metric type {:?} in a metric pattern: {:?}", ty, s),
+        };
+        let (name, regex) = rest
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("No regex part in a metric pattern: {:?}", s))?;
+        Ok(Self {
+            ty,
+            name: MetricName::new(name.to_owned()),
+            regex: Regex::new(regex)?,
+        })
+    }
+}
+
+impl std::fmt::Display for MetricPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let ty = match self.ty {
+            MetricType::Minimize => "minimize",
+            MetricType::Maximize => "maximize",
+            MetricType::Record => "record",
+        };
+        write!(f, "{}:{}={}", ty, self.name.get(), self.regex.as_str())
+    }
+}
+
+impl std::convert::TryFrom<String> for MetricPattern {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<MetricPattern> for String {
+    fn from(f: MetricPattern) -> Self {
+        f.to_string()
+    }
+}
+
+/// A channel to send extracted metrics to the runner, which is closed when the observation exits.
+///
+/// The threads reading the output may outlive the observation
+/// (e.g., a background grandchild process inherits the output),
+/// so the metrics are only sent while it is open, and the runner never receives ones for finished observations.
+#[derive(Debug, Clone)]
+pub struct MetricChannel {
+    obs_id: ObservationId,
+    event_tx: Sender<RunnerEvent>,
+    is_open: Arc<Mutex<bool>>,
+}
+
+impl MetricChannel {
+    pub fn new(obs_id: ObservationId, event_tx: Sender<RunnerEvent>) -> Self {
+        Self {
+            obs_id,
+            event_tx,
+            is_open: Arc::new(Mutex::new(true)),
+        }
+    }
+
+    fn send(&self, pattern: &MetricPattern, value: MetricValue) {
+        let is_open = self.is_open.lock().unwrap_or_else(|e| panic!("{}", e));
+        if *is_open {
+            let _ = self.event_tx.send(RunnerEvent::Metric {
+                obs_id: self.obs_id,
+                name: pattern.name.clone(),
+                ty: pattern.ty,
+                value,
+            });
+        }
+    }
+
+    /// Closes the channel after sending `event`.
+    pub fn close_with(&self, event: RunnerEvent) {
+        let mut is_open = self.is_open.lock().unwrap_or_else(|e| panic!("{}", e));
+        *is_open = false;
+        let _ = self.event_tx.send(event);
+    }
+}
+
+/// Spawns a thread that copies `output` to the standard error and extracts metrics from it.
+///
+/// `done` is dropped when `output` reaches EOF.
+pub fn spawn_extractor<R: Read + Send + 'static>(
+    output: R,
+    patterns: Vec<MetricPattern>,
+    channel: MetricChannel,
+    done: Sender<()>,
+) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(move || {
+            let _done = done;
+            let mut reader = BufReader::new(output);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {}
+                }
+                let _ = std::io::stderr().write_all(&buf);
+
+                let line = String::from_utf8_lossy(&buf);
+                for pattern in &patterns {
+                    if let Some(value) = pattern.extract(&line) {
+                        channel.send(pattern, value);
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use crate::trial::FailureReason;
+use crate::types::{ByteSize, ElapsedSeconds};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The interval of checking the memory usage of observations.
+pub const MEMORY_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ResourceLimits {
+    pub memory: Option<ByteSize>,
+    pub cpu_time: Option<ElapsedSeconds>,
+    pub open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Applies the rlimits to the current process.
+    ///
+    /// This is called in a forked child process before `exec`,
+    /// so it must not allocate memory.
+    /// The memory limit isn't an rlimit, but is checked by `StudyRunner` for the whole process group.
+    pub fn apply(&self) -> std::io::Result<()> {
+        if let Some(cpu_time) = self.cpu_time {
+            // `SIGXCPU` is sent when the soft limit is reached, and `SIGKILL` for the hard limit.
+            let seconds = cpu_time.get().ceil() as u64;
+            set_rlimit(libc::RLIMIT_CPU, seconds, seconds + 1)?;
+        }
+        if let Some(n) = self.open_files {
+            set_rlimit(libc::RLIMIT_NOFILE, n, n)?;
+        }
+        Ok(())
+    }
+
+    /// Guesses why an observation failed from how its process exited.
+    pub fn failure_reason(&self, exit: &ExitInfo) -> Option<FailureReason> {
+        if exit.code == Some(0) {
+            return None;
+        }
+
+        let cpu_time_exceeded = self
+            .cpu_time
+            .is_some_and(|t| exit.cpu_time.get() >= t.get().ceil());
+        if exit.signal == Some(libc::SIGXCPU) || (exit.signal.is_some() && cpu_time_exceeded) {
+            Some(FailureReason::CpuTimeLimitExceeded)
+        } else {
+            exit.signal.map(|signal| FailureReason::Signaled { signal })
+        }
+    }
+}
+
+/// Returns the total resident set size of the processes in each process group.
+pub fn rss_by_process_group() -> std::io::Result<HashMap<libc::pid_t, ByteSize>> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    let mut groups = HashMap::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let path = entry?.path().join("stat");
+        // Processes may exit while scanning.
+        if let Some((pgid, pages)) = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|stat| parse_stat(&stat))
+        {
+            *groups.entry(pgid).or_insert(0) += pages * page_size;
+        }
+    }
+    Ok(groups
+        .into_iter()
+        .map(|(k, v)| (k, ByteSize::new(v)))
+        .collect())
+}
+
+/// Returns the process group ID and the resident set size (in pages) in the content of `/proc/PID/stat`.
+fn parse_stat(stat: &str) -> Option<(libc::pid_t, u64)> {
+    // The command name in parentheses may contain spaces, so the fields after it are counted.
+    let mut fields = stat.get(stat.rfind(')')? + 1..)?.split_whitespace();
+    let pgid = fields.nth(2)?.parse().ok()?;
+    let rss = fields.nth(18)?.parse().ok()?;
+    Some((pgid, rss))
+}
+
+#[derive(Debug, Clone)]
+pub struct ExitInfo {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    pub cpu_time: ElapsedSeconds,
+}
+
+impl ExitInfo {
+    /// Waits for the process to exit.
+    pub fn wait(pid: libc::pid_t) -> std::io::Result<Self> {
+        let mut status = 0;
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            if unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) } != -1 {
+                break;
+            }
+            let e = std::io::Error::last_os_error();
+            if e.kind() != std::io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+
+        let timeval = |t: libc::timeval| t.tv_sec as f64 + t.tv_usec as f64 / 1_000_000.0;
+        Ok(Self {
+            code: libc::WIFEXITED(status).then(|| libc::WEXITSTATUS(status)),
+            signal: libc::WIFSIGNALED(status).then(|| libc::WTERMSIG(status)),
+            cpu_time: ElapsedSeconds::new(timeval(rusage.ru_utime) + timeval(rusage.ru_stime)),
+        })
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type Resource = libc::c_int;
+
+fn set_rlimit(resource: Resource, soft: u64, hard: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource, &limit) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stat_works() {
+        let stat = "1234 (a (b) c) S 1 1230 1230 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 1 0 100 12345678 321 18446744073709551615";
+        assert_eq!(parse_stat(stat), Some((1230, 321)));
+        assert_eq!(parse_stat("1234 (a) S 1"), None);
+    }
+
+    #[test]
+    fn own_process_group_is_found() -> std::io::Result<()> {
+        let pgid = unsafe { libc::getpgrp() };
+        let groups = rss_by_process_group()?;
+        assert!(groups[&pgid].get() > 0);
+        Ok(())
+    }
+}
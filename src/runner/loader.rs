@@ -96,7 +96,8 @@ impl<'a, W: Write> StudyLoader<'a, W> {
                     .ok_or_else(|| anyhow::anyhow!("unknown trial id {:?}", obs.trial_id))?;
                 obs.id = obs_id;
                 obs.trial_id = trial_id;
-                self.study.tell_finished_obs(obs, elapsed.to_duration())?;
+                self.study
+                    .tell_finished_obs(obs, elapsed.to_duration(), true)?;
             }
         }
         Ok(())
@@ -0,0 +1,58 @@
+use std::collections::BTreeSet;
+
+/// A resource shared by the workers (e.g., `CUDA_VISIBLE_DEVICES=0,1,2,3`).
+///
+/// The worker of slot `i` is given `values[i % values.len()]` via the environment variable `name`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkerResource {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl WorkerResource {
+    pub fn get(&self, worker_id: usize) -> &str {
+        &self.values[worker_id % self.values.len()]
+    }
+}
+
+impl std::str::FromStr for WorkerResource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut iter = s.splitn(2, '=');
+        let name = iter.next().expect("unreachable");
+        let values = iter
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No value part in a resource string: {:?}", s))?;
+        anyhow::ensure!(!name.is_empty(), "Empty resource name: {:?}", s);
+        Ok(Self {
+            name: name.to_owned(),
+            values: values.split(',').map(|v| v.to_owned()).collect(),
+        })
+    }
+}
+
+/// Worker slot IDs not used by running observations.
+#[derive(Debug)]
+pub struct WorkerSlots {
+    free: BTreeSet<usize>,
+}
+
+impl WorkerSlots {
+    pub fn new(workers: usize) -> Self {
+        Self {
+            free: (0..workers).collect(),
+        }
+    }
+
+    /// Takes the smallest free slot ID.
+    pub fn acquire(&mut self) -> Option<usize> {
+        let id = *self.free.iter().next()?;
+        self.free.remove(&id);
+        Some(id)
+    }
+
+    pub fn release(&mut self, id: usize) {
+        self.free.insert(id);
+    }
+}
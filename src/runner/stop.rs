@@ -0,0 +1,130 @@
+use crate::metric::{MetricName, MetricType, MetricValue};
+use crate::trial::Observation;
+use crate::types::ElapsedSeconds;
+use std::collections::BTreeMap;
+
+/// A condition on a metric value such as `loss<=0.01`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MetricThreshold {
+    pub metric: MetricName,
+    pub op: CompareOp,
+    pub value: f64,
+}
+
+impl MetricThreshold {
+    pub fn is_satisfied(&self, obs: &Observation) -> bool {
+        obs.metrics.get(&self.metric).is_some_and(|m| {
+            let v = m.value.get();
+            match self.op {
+                CompareOp::Le => v <= self.value,
+                CompareOp::Ge => v >= self.value,
+                CompareOp::Lt => v < self.value,
+                CompareOp::Gt => v > self.value,
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for MetricThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.metric.get(), self.op.as_str(), self.value)
+    }
+}
+
+impl std::str::FromStr for MetricThreshold {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for op in [CompareOp::Le, CompareOp::Ge, CompareOp::Lt, CompareOp::Gt] {
+            if let Some((metric, value)) = s.split_once(op.as_str()) {
+                let metric = metric.trim();
+                anyhow::ensure!(!metric.is_empty(), "No metric name in a condition: {:?}", s);
+                let value = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid value in a condition: {:?}", s))?;
+                return Ok(Self {
+                    metric: MetricName::new(metric.to_owned()),
+                    op,
+                    value,
+                });
+            }
+        }
+        anyhow::bail!(
+            "No comparison operator (`<=`, `>=`, `<` or `>`) in a condition: {:?}",
+            s
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOp {
+    Le,
+    Ge,
+    Lt,
+    Gt,
+}
+
+impl CompareOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Le => "<=",
+            Self::Ge => ">=",
+            Self::Lt => "<",
+            Self::Gt => ">",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StopConditions {
+    /// Stops when a finished observation satisfies any of the conditions.
+    pub stop_when: Vec<MetricThreshold>,
+
+    /// Stops after this number of consecutive trials that don't improve any objective metric.
+    pub patience: Option<usize>,
+
+    /// Stops starting new trials when this number of trials (including loaded ones) have started.
+    pub max_trials: Option<usize>,
+
+    /// Stops when this time has elapsed since the runner started.
+    pub time_budget: Option<ElapsedSeconds>,
+}
+
+/// Tracks the best objective values to count trials without improvement.
+#[derive(Debug, Default)]
+pub struct PatienceCounter {
+    best: BTreeMap<MetricName, MetricValue>,
+    count: usize,
+}
+
+impl PatienceCounter {
+    /// Returns the number of consecutive trials without improvement.
+    pub fn update(&mut self, obs: &Observation) -> usize {
+        if !obs.is_max_fidelity() {
+            return self.count;
+        }
+
+        let mut improved = false;
+        for (name, metric) in &obs.metrics {
+            if metric.ty == MetricType::Record {
+                continue;
+            }
+            let better = self
+                .best
+                .get(name)
+                .map_or(true, |&best| metric.is_better_than(best));
+            if better {
+                self.best.insert(name.clone(), metric.value);
+                improved = true;
+            }
+        }
+        if improved {
+            self.count = 0;
+        } else {
+            self.count += 1;
+        }
+        self.count
+    }
+}
@@ -1,6 +1,6 @@
 use crate::trial::{ObservationId, TrialId};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
 #[derive(Debug)]
@@ -51,6 +51,32 @@ impl TempDirs {
         Ok(path)
     }
 
+    /// Replaces the contents of the temporary directory of `to` with those of `from`.
+    pub fn copy_trial_tempdir(&mut self, from: TrialId, to: TrialId) -> anyhow::Result<()> {
+        let src = self
+            .trials
+            .get(&from)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "the trial {} has no temporary directory to copy from",
+                    from.get()
+                )
+            })?
+            .path()
+            .to_path_buf();
+        let parent = src.parent().map(|p| p.to_path_buf());
+        let dst = self.create_trial_tempdir(to, parent.as_ref())?;
+        for entry in std::fs::read_dir(&dst)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                std::fs::remove_dir_all(entry.path())?;
+            } else {
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        copy_dir(&src, &dst)
+    }
+
     pub fn remove_trial_tempdir(&mut self, id: TrialId) {
         self.trials.remove(&id);
     }
@@ -77,3 +103,49 @@ impl TempDirs {
         }
     }
 }
+
+fn copy_dir(src: &Path, dst: &Path) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let to = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            std::fs::create_dir(&to)?;
+            copy_dir(&entry.path(), &to)?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &to)?;
+        } else {
+            std::fs::copy(entry.path(), &to)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_trial_tempdir_replaces_contents() -> anyhow::Result<()> {
+        let mut tempdirs = TempDirs::new();
+        let (from, to) = (TrialId::new(0), TrialId::new(1));
+        let src = tempdirs.create_trial_tempdir(from, None)?;
+        std::fs::create_dir(src.join("sub"))?;
+        std::fs::write(src.join("sub/checkpoint"), "new")?;
+        let dst = tempdirs.create_trial_tempdir(to, None)?;
+        std::fs::write(dst.join("stale"), "old")?;
+
+        tempdirs.copy_trial_tempdir(from, to)?;
+        assert!(!dst.join("stale").exists());
+        assert_eq!(std::fs::read_to_string(dst.join("sub/checkpoint"))?, "new");
+        Ok(())
+    }
+
+    #[test]
+    fn copy_from_missing_trial_tempdir_fails() {
+        let mut tempdirs = TempDirs::new();
+        assert!(tempdirs
+            .copy_trial_tempdir(TrialId::new(0), TrialId::new(1))
+            .is_err());
+    }
+}
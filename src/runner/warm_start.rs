@@ -0,0 +1,190 @@
+use crate::event::{Event, EventReader, ObservationEvent, StudyEvent};
+use crate::param::{ParamInstance, ParamName, ParamType};
+use crate::trial::{Observation, ObservationId, TrialId};
+use crate::tuners::Action;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::BufRead;
+
+#[derive(Debug, Clone)]
+pub struct ParamRename {
+    pub from: ParamName,
+    pub to: ParamName,
+}
+
+impl std::str::FromStr for ParamRename {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut iter = s.splitn(2, '=');
+        let from = iter.next().expect("unreachable");
+        let to = iter
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No new name part in a rename string: {:?}", s))?;
+        Ok(Self {
+            from: ParamName::new(from.to_owned()),
+            to: ParamName::new(to.to_owned()),
+        })
+    }
+}
+
+/// Historical observations that are told to the tuner as prior knowledge.
+///
+/// The observations are told before the first ask, so the current types are only known for
+/// the parameters of the command template and the ones of the loaded studies.
+/// The observations that have a value outside of these types are discarded,
+/// and the other parameters, which are asked at runtime, are told with their recorded types.
+///
+/// The observations are given IDs counting down from `u64::MAX` so that they never collide
+/// with the ones of the current study.
+#[derive(Debug)]
+pub struct WarmStart {
+    pending: Vec<Observation>,
+    param_types: BTreeMap<ParamName, ParamType>,
+    trial_ids: HashSet<TrialId>,
+    next_obs_id: u64,
+    next_trial_id: u64,
+}
+
+impl WarmStart {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+            param_types: BTreeMap::new(),
+            trial_ids: HashSet::new(),
+            next_obs_id: u64::MAX,
+            next_trial_id: u64::MAX,
+        }
+    }
+
+    pub fn load<R: BufRead>(
+        &mut self,
+        mut reader: EventReader<R>,
+        renames: &[ParamRename],
+    ) -> anyhow::Result<()> {
+        let mut trial_id_mapping = HashMap::new();
+        while let Some(event) = reader.read()? {
+            match event {
+                Event::Study(StudyEvent::Defined { .. }) => {
+                    trial_id_mapping = HashMap::new();
+                }
+                Event::Observation(ObservationEvent::Finished { mut obs, .. }) => {
+                    if !obs.is_succeeded() {
+                        continue;
+                    }
+
+                    let next_trial_id = &mut self.next_trial_id;
+                    let trial_id = *trial_id_mapping.entry(obs.trial_id).or_insert_with(|| {
+                        *next_trial_id -= 1;
+                        TrialId::new(*next_trial_id + 1)
+                    });
+                    self.trial_ids.insert(trial_id);
+                    obs.trial_id = trial_id;
+                    obs.id = ObservationId::new(self.next_obs_id);
+                    self.next_obs_id -= 1;
+
+                    for rename in renames {
+                        if let Some(p) = obs.params.remove(&rename.from) {
+                            obs.params.insert(rename.to.clone(), p);
+                        }
+                        for name in &mut obs.ask_order {
+                            if *name == rename.from {
+                                *name = rename.to.clone();
+                            }
+                        }
+                    }
+                    self.pending.push(obs);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_warm_action(&self, action: &Action) -> bool {
+        match action {
+            Action::ResumeTrial { trial_id }
+            | Action::FinishTrial { trial_id }
+            | Action::CopyTrialDir { to: trial_id, .. } => self.trial_ids.contains(trial_id),
+            _ => false,
+        }
+    }
+
+    /// Registers the type of a parameter of the current study.
+    pub fn learn(&mut self, name: &ParamName, ty: &ParamType) {
+        self.param_types.insert(name.clone(), ty.clone());
+    }
+
+    /// Returns all the pending observations whose values fit the known parameter types.
+    pub fn take_ready(&mut self) -> Vec<Observation> {
+        let mut ready = Vec::new();
+        'obs: for mut obs in std::mem::take(&mut self.pending) {
+            if obs.params.is_empty() {
+                continue;
+            }
+            for (name, p) in &mut obs.params {
+                let ty = if let Some(ty) = self.param_types.get(name) {
+                    ty
+                } else {
+                    continue;
+                };
+                if let Ok(value) = ty.normalize(&p.value) {
+                    *p = ParamInstance::new(ty.clone(), value);
+                } else {
+                    continue 'obs;
+                }
+            }
+            ready.push(obs);
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{DiscreteParamType, NumParamType};
+
+    const LOG: &str = r#"{"study":{"defined":{"name":"a","id":"4a8e7dae-2d6d-4636-9e72-50fdfcecc192","attrs":{},"tuner":{"retry":0},"command":{"path":"true","args":[]}}}}
+{"observation":{"finished":{"obs_id":0,"trial_id":0,"params":{"x":{"ty":{"discrete":{"range":{"min":0.0,"max":10.0},"step":1.0,"ln":false}},"value":3.0},"old":{"ty":{"discrete":{"range":{"min":0.0,"max":10.0},"step":1.0,"ln":false}},"value":1.0}},"ask_order":["x","old"],"metrics":{},"exit_status":0,"elapsed":1.0}}}
+{"observation":{"finished":{"obs_id":1,"trial_id":1,"params":{"x":{"ty":{"discrete":{"range":{"min":0.0,"max":10.0},"step":1.0,"ln":false}},"value":8.0}},"ask_order":["x"],"metrics":{},"exit_status":0,"elapsed":2.0}}}
+{"observation":{"finished":{"obs_id":2,"trial_id":2,"params":{"x":{"ty":{"discrete":{"range":{"min":0.0,"max":10.0},"step":1.0,"ln":false}},"value":2.0}},"ask_order":["x"],"metrics":{},"exit_status":1,"elapsed":3.0}}}
+"#;
+
+    fn x_type(max: f64) -> ParamType {
+        let ty = DiscreteParamType::new(0.0, max, 1.0, false, None).unwrap();
+        ParamType::Num(NumParamType::Discrete(ty))
+    }
+
+    #[test]
+    fn observations_are_checked_against_the_known_params() -> anyhow::Result<()> {
+        let mut warm_start = WarmStart::new();
+        warm_start.load(EventReader::new(LOG.as_bytes()), &[])?;
+        warm_start.learn(&ParamName::new("x".to_owned()), &x_type(5.0));
+
+        // `x=8` is outside of the current type, and `old` is told with the recorded type.
+        let ready = warm_start.take_ready();
+        assert_eq!(ready.len(), 1);
+        let obs = &ready[0];
+        assert_eq!(obs.params.len(), 2);
+        assert_eq!(obs.params[&obs.ask_order[0]].ty, x_type(5.0));
+        assert_eq!(obs.params[&obs.ask_order[1]].ty, x_type(10.0));
+        assert!(warm_start.is_warm_action(&Action::finish_trial(obs.trial_id)));
+        assert!(warm_start.take_ready().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn renamed_params_are_kept() -> anyhow::Result<()> {
+        let mut warm_start = WarmStart::new();
+        let renames = ["old=y".parse()?];
+        warm_start.load(EventReader::new(LOG.as_bytes()), &renames)?;
+        warm_start.learn(&ParamName::new("x".to_owned()), &x_type(10.0));
+        warm_start.learn(&ParamName::new("y".to_owned()), &x_type(10.0));
+
+        let ready = warm_start.take_ready();
+        assert_eq!(ready.len(), 2);
+        let names: Vec<_> = ready[0].ask_order.iter().map(|n| n.get()).collect();
+        assert_eq!(names, ["x", "y"]);
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
-use crate::tuners::TunerSpec;
+use crate::param::{ParamInstance, ParamName, ParamType, ParamValue};
+use crate::runner::{MetricMatches, MetricPattern, ResourceLimits};
+use crate::tuners::constraint::{ConstraintTuner, ParamConstraint};
+use crate::tuners::fix::FixTuner;
+use crate::tuners::{Tuner, TunerSpec};
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use uuid::Uuid;
@@ -9,11 +13,170 @@ pub struct StudySpec {
     pub id: Uuid,
     pub attrs: BTreeMap<String, String>,
     pub tuner: TunerSpec,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fixed_params: BTreeMap<ParamName, ParamValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<ParamConstraint>,
     pub command: CommandSpec,
+    #[serde(default)]
+    pub observation: ObservationSpec,
+}
+
+impl StudySpec {
+    pub fn build_tuner(&self) -> anyhow::Result<Tuner> {
+        let mut tuner = self.tuner.build()?;
+        if !self.fixed_params.is_empty() {
+            tuner = Tuner::new(FixTuner::new(tuner, self.fixed_params.clone()));
+        }
+        if !self.constraints.is_empty() {
+            self.check_constraints()?;
+            tuner = Tuner::new(ConstraintTuner::new(tuner, self.constraints.clone()));
+        }
+        Ok(tuner)
+    }
+
+    /// Ensures that the constraints can be satisfied by resampling, refer only to numeric template parameters,
+    /// and aren't violated by the fixed or enqueued parameters.
+    fn check_constraints(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !self.tuner.is_deterministic(),
+            "constraints can't be used with deterministic tuners (`sobol`, `halton`, `lhs` or `pbt`), \
+             which can't resample values"
+        );
+        let fixed = |name: &ParamName| self.fixed_params.get(name);
+        for constraint in &self.constraints {
+            for p in &self.command.params {
+                constraint.check_param_type(&p.name, &p.ty)?;
+            }
+            anyhow::ensure!(
+                constraint.is_satisfied(fixed)? != Some(false),
+                "the fixed parameters violate the constraint {:?}",
+                constraint.to_string()
+            );
+            for enqueued in self.tuner.enqueued() {
+                let lookup = |name: &ParamName| enqueued.get(name).or_else(|| fixed(name));
+                anyhow::ensure!(
+                    constraint.is_satisfied(lookup)? != Some(false),
+                    "the enqueued parameters {} violate the constraint {:?}",
+                    serde_json::to_string(enqueued)?,
+                    constraint.to_string()
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CommandSpec {
     pub path: PathBuf,
     pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<TemplateParam>,
+}
+
+impl CommandSpec {
+    /// Replaces the `{NAME}` placeholders of the template parameters in the arguments.
+    pub fn render_args(&self, params: &BTreeMap<ParamName, ParamInstance>) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|arg| {
+                let mut arg = arg.clone();
+                for p in &self.params {
+                    if let Some(instance) = params.get(&p.name) {
+                        let placeholder = format!("{{{}}}", p.name.get());
+                        arg = arg.replace(&placeholder, &instance.value.to_string());
+                    }
+                }
+                arg
+            })
+            .collect()
+    }
+}
+
+/// How each observation is run and what is recorded for it.
+///
+/// This is a part of the study so that `hone rerun` runs a trial in the same way.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ObservationSpec {
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metric_patterns: Vec<MetricPattern>,
+    #[serde(default)]
+    pub metric_matches: MetricMatches,
+    #[serde(default)]
+    pub minimize_elapsed: bool,
+    #[serde(default)]
+    pub metric_exit_code: bool,
+}
+
+/// A parameter asked by the runner before spawning a command (e.g., `lr=range 0.0001 1 --ln`).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TemplateParam {
+    pub name: ParamName,
+    pub ty: ParamType,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn study(tuner: &str, fix: &str, constraint: &str) -> anyhow::Result<StudySpec> {
+        let json = format!(
+            r#"{{"name":"test","id":"8c3f6b7a-2f44-4b8e-9d4e-0e6f3d4a1b2c","attrs":{{}},
+                "tuner":{},"fixed_params":{},"constraints":[{:?}],
+                "command":{{"path":"true","args":[]}}}}"#,
+            tuner, fix, constraint
+        );
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    #[test]
+    fn constraints_require_resampling_tuners() -> anyhow::Result<()> {
+        assert!(study(r#"{"random":{}}"#, "{}", "a < b")?
+            .build_tuner()
+            .is_ok());
+        assert!(study(r#"{"sobol":{}}"#, "{}", "a < b")?
+            .build_tuner()
+            .is_err());
+        let chain =
+            r#"{"chain":{"stages":[{"tuner":{"lhs":{"samples":10}},"trials":10},{"tuner":{}}]}}"#;
+        assert!(study(chain, "{}", "a < b")?.build_tuner().is_err());
+        let pbt = r#"{"pbt":{"population":4,"quantile":0.25,"resample_probability":0.25}}"#;
+        assert!(study(pbt, "{}", "a < b")?.build_tuner().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn constraint_params_must_be_numeric() -> anyhow::Result<()> {
+        let mut spec = study("{}", "{}", "a < b")?;
+        spec.command.params.push("a=choice 1 2.5".parse()?);
+        assert!(spec.build_tuner().is_ok());
+        spec.command.params.push("b=choice adam sgd".parse()?);
+        assert!(spec.build_tuner().is_err());
+
+        assert!(study("{}", r#"{"b":"adam"}"#, "a < b")?
+            .build_tuner()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_and_enqueued_params_must_satisfy_constraints() -> anyhow::Result<()> {
+        assert!(study("{}", r#"{"a":1,"b":2}"#, "a < b")?
+            .build_tuner()
+            .is_ok());
+        assert!(study("{}", r#"{"a":3,"b":2}"#, "a < b")?
+            .build_tuner()
+            .is_err());
+
+        let enqueued = r#"{"enqueue":[{"a":1},{"a":3,"b":2}]}"#;
+        assert!(study(enqueued, "{}", "a < b")?.build_tuner().is_err());
+        let enqueued = r#"{"enqueue":[{"a":3}]}"#;
+        assert!(study(enqueued, r#"{"b":2}"#, "a < b")?
+            .build_tuner()
+            .is_err());
+        Ok(())
+    }
 }
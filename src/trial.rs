@@ -1,6 +1,6 @@
 use crate::metric::{MetricInstance, MetricName, MetricValue};
 use crate::param::{ParamInstance, ParamName, ParamValue};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
@@ -50,8 +50,21 @@ pub struct Observation {
     pub id: ObservationId,
     pub trial_id: TrialId,
     pub params: BTreeMap<ParamName, ParamInstance>,
+    #[serde(default)]
+    pub ask_order: Vec<ParamName>,
     pub metrics: BTreeMap<MetricName, MetricInstance>,
+    /// Every value extracted from the output for each metric, in order (see `--metric-matches all`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub intermediate_metrics: BTreeMap<MetricName, Vec<MetricValue>>,
     pub exit_status: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureReason>,
+    /// The observation whose result was reused instead of running the command (see `--dedupe cache`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached: Option<ObservationId>,
+    /// The already-finished observation with the same parameters, found after the command has run (see `--dedupe`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<ObservationId>,
 }
 
 impl Observation {
@@ -60,11 +73,33 @@ impl Observation {
             id: obs_id,
             trial_id,
             params: BTreeMap::new(),
+            ask_order: Vec::new(),
             metrics: BTreeMap::new(),
+            intermediate_metrics: BTreeMap::new(),
             exit_status: None,
+            failure: None,
+            cached: None,
+            duplicate_of: None,
         }
     }
 
+    pub fn insert_param(&mut self, name: ParamName, param: ParamInstance) {
+        if self.params.insert(name.clone(), param).is_none() {
+            self.ask_order.push(name);
+        }
+    }
+
+    /// Returns the parameters in the order they were asked.
+    pub fn asked_params(&self) -> impl Iterator<Item = (&ParamName, &ParamInstance)> {
+        self.ask_order
+            .iter()
+            .filter_map(move |name| self.params.get_key_value(name))
+    }
+
+    pub fn param_names(&self) -> BTreeSet<ParamName> {
+        self.params.keys().cloned().collect()
+    }
+
     pub fn is_succeeded(&self) -> bool {
         self.exit_status == Some(0)
     }
@@ -89,12 +124,24 @@ impl Observation {
             metrics: self
                 .metrics
                 .iter()
-                .map(|(k, v)| (k.clone(), v.value.clone()))
+                .map(|(k, v)| (k.clone(), v.value))
                 .collect(),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureReason {
+    Signaled {
+        signal: i32,
+    },
+    MemoryLimitExceeded,
+    CpuTimeLimitExceeded,
+    /// Killed because no value of a parameter satisfied the constraints (see `--param-constraint`).
+    ConstraintUnsatisfied,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CompactObservation {
     #[serde(rename = "obs_id")]
@@ -103,3 +150,49 @@ pub struct CompactObservation {
     pub params: BTreeMap<ParamName, ParamValue>,
     pub metrics: BTreeMap<MetricName, MetricValue>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{CategoricalParamType, ParamType, StrParamType};
+
+    fn instance(value: &str) -> ParamInstance {
+        let ty = CategoricalParamType::new(vec!["a".to_owned(), "b".to_owned()], None).unwrap();
+        ParamInstance::new(
+            ParamType::Str(StrParamType::Categorical(ty)),
+            ParamValue::Str(value.to_owned()),
+        )
+    }
+
+    #[test]
+    fn insert_param_records_ask_order() {
+        let mut obs = Observation::new(ObservationId::new(0), TrialId::new(0));
+        obs.insert_param(ParamName::new("y".to_owned()), instance("a"));
+        obs.insert_param(ParamName::new("x".to_owned()), instance("a"));
+        obs.insert_param(ParamName::new("y".to_owned()), instance("b"));
+
+        let order: Vec<_> = obs.ask_order.iter().map(|n| n.get()).collect();
+        assert_eq!(order, ["y", "x"]);
+        assert_eq!(obs.params.len(), 2);
+
+        let asked: Vec<_> = obs
+            .asked_params()
+            .map(|(n, p)| (n.get(), p.value.clone()))
+            .collect();
+        assert_eq!(
+            asked,
+            [
+                ("y", ParamValue::Str("b".to_owned())),
+                ("x", ParamValue::Str("a".to_owned()))
+            ]
+        );
+    }
+
+    #[test]
+    fn ask_order_defaults_to_empty_for_old_logs() {
+        let json = r#"{"obs_id":1,"trial_id":2,"params":{},"metrics":{},"exit_status":0}"#;
+        let obs: Observation = serde_json::from_str(json).unwrap();
+        assert!(obs.ask_order.is_empty());
+        assert!(obs.is_succeeded());
+    }
+}
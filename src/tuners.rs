@@ -2,8 +2,19 @@ use crate::param::{ParamName, ParamType, ParamValue};
 use crate::trial::{Observation, TrialId};
 use std::collections::VecDeque;
 
+pub mod chain;
+pub mod constraint;
+pub mod enqueue;
+pub mod fix;
+pub mod groups;
+pub mod halton;
+pub mod lhs;
+pub mod pbt;
 pub mod random;
+pub mod replay;
 pub mod retry;
+pub mod sequence;
+pub mod sobol;
 
 pub trait Tune {
     fn ask(
@@ -16,12 +27,18 @@ pub trait Tune {
     fn tell(&mut self, obs: &Observation) -> anyhow::Result<()>;
 
     fn next_action(&mut self) -> Option<Action>;
+
+    /// Notifies the ID of the trial started by the last `None` returned from `next_action`.
+    ///
+    /// Trials that weren't started by the tuner (e.g., loaded or warm-start ones) are never notified.
+    fn start_trial(&mut self, _trial_id: TrialId) {}
 }
 
 #[derive(Debug, Clone)]
 pub enum Action {
     ResumeTrial { trial_id: TrialId },
     FinishTrial { trial_id: TrialId },
+    CopyTrialDir { from: TrialId, to: TrialId },
     WaitObservations,
     QuitOptimization,
 }
@@ -34,9 +51,13 @@ impl Action {
     pub const fn finish_trial(trial_id: TrialId) -> Self {
         Self::FinishTrial { trial_id }
     }
+
+    pub const fn copy_trial_dir(from: TrialId, to: TrialId) -> Self {
+        Self::CopyTrialDir { from, to }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ActionQueue(VecDeque<Action>);
 
 impl ActionQueue {
@@ -48,6 +69,7 @@ impl ActionQueue {
         self.0.push_back(action);
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<Action> {
         self.0.pop_front()
     }
@@ -58,12 +80,31 @@ impl ActionQueue {
 enum TunerSpecInner {
     // TODO:  HyperbandTuner, TpeTuner
     Random(self::random::RandomTunerSpec),
+    Sobol(self::sobol::SobolTunerSpec),
+    Halton(self::halton::HaltonTunerSpec),
+    Lhs(self::lhs::LhsTunerSpec),
+    Chain(self::chain::ChainTunerSpec),
+    Pbt(self::pbt::PbtTunerSpec),
 }
 
 impl TunerSpecInner {
     pub fn build(&self) -> anyhow::Result<Tuner> {
         match self {
             Self::Random(spec) => spec.build().map(Tuner::new),
+            Self::Sobol(spec) => spec.build().map(Tuner::new),
+            Self::Halton(spec) => spec.build().map(Tuner::new),
+            Self::Lhs(spec) => spec.build().map(Tuner::new),
+            Self::Chain(spec) => spec.build().map(Tuner::new),
+            Self::Pbt(spec) => spec.build().map(Tuner::new),
+        }
+    }
+
+    fn is_deterministic(&self) -> bool {
+        match self {
+            // PBT returns the parameters of the population member for the trial.
+            Self::Sobol(_) | Self::Halton(_) | Self::Lhs(_) | Self::Pbt(_) => true,
+            Self::Chain(spec) => spec.stages.iter().any(|s| s.tuner.is_deterministic()),
+            Self::Random(_) => false,
         }
     }
 }
@@ -78,8 +119,13 @@ impl Default for TunerSpecInner {
 #[serde(rename_all = "snake_case")]
 pub struct TunerSpec {
     #[clap(long, default_value = "0")]
+    #[serde(default)]
     retry: usize,
 
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    enqueue: Vec<self::enqueue::EnqueuedParams>,
+
     // TODO: AverageTuner, HyperbandTuner, TpeTuner
     #[clap(subcommand)]
     #[serde(flatten)]
@@ -90,11 +136,30 @@ impl TunerSpec {
     pub fn build(&self) -> anyhow::Result<Tuner> {
         let default_tuner = TunerSpecInner::Random(self::random::RandomTunerSpec::default());
         let mut tuner = self.inner.as_ref().unwrap_or(&default_tuner).build()?;
+        if !self.enqueue.is_empty() {
+            tuner = Tuner::new(self::enqueue::EnqueueTuner::new(
+                tuner,
+                self.enqueue.clone(),
+            ));
+        }
         if self.retry > 0 {
             tuner = Tuner::new(self::retry::RetryTuner::new(tuner, self.retry));
         }
         Ok(tuner)
     }
+
+    pub fn enqueue(&mut self, params: self::enqueue::EnqueuedParams) {
+        self.enqueue.push(params);
+    }
+
+    pub fn enqueued(&self) -> &[self::enqueue::EnqueuedParams] {
+        &self.enqueue
+    }
+
+    /// Returns `true` if the tuner proposes the same value when asked again for a parameter of an observation.
+    pub fn is_deterministic(&self) -> bool {
+        self.inner.as_ref().is_some_and(|t| t.is_deterministic())
+    }
 }
 
 impl std::str::FromStr for TunerSpec {
@@ -133,6 +198,10 @@ impl Tune for Tuner {
     fn next_action(&mut self) -> Option<Action> {
         self.0.next_action()
     }
+
+    fn start_trial(&mut self, trial_id: TrialId) {
+        self.0.start_trial(trial_id)
+    }
 }
 
 impl std::fmt::Debug for Tuner {
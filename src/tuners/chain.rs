@@ -0,0 +1,301 @@
+use crate::param::{ParamName, ParamType, ParamValue};
+use crate::trial::{Observation, TrialId};
+use crate::tuners::{Action, Tune, Tuner, TunerSpec};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, clap::Args, serde::Serialize, serde::Deserialize)]
+pub struct ChainTunerSpec {
+    #[clap(long = "stage")]
+    pub stages: Vec<ChainStageSpec>,
+}
+
+impl ChainTunerSpec {
+    pub fn build(&self) -> anyhow::Result<ChainTuner> {
+        anyhow::ensure!(!self.stages.is_empty(), "no stages are specified");
+        let stages = self
+            .stages
+            .iter()
+            .map(|s| {
+                Ok(Stage {
+                    tuner: s.tuner.build()?,
+                    budget: s.trials,
+                    started_trials: 0,
+                    finished: false,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(ChainTuner::new(stages))
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChainStageSpec {
+    pub tuner: TunerSpec,
+
+    /// The number of trials started by this stage before switching to the next one.
+    #[serde(default)]
+    pub trials: Option<usize>,
+}
+
+impl std::str::FromStr for ChainStageSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::json::parse_json(s)
+    }
+}
+
+#[derive(Debug)]
+struct Stage {
+    tuner: Tuner,
+    budget: Option<usize>,
+    started_trials: usize,
+    finished: bool,
+}
+
+impl Stage {
+    fn is_exhausted(&self) -> bool {
+        self.finished || self.budget.is_some_and(|n| self.started_trials >= n)
+    }
+}
+
+/// A tuner that switches the underlying tuner after the trial budget of a stage is consumed
+/// (or the tuner of the stage quits the optimization).
+///
+/// Each trial is handled by the stage that started it,
+/// and the results of the trial are told to that stage and all the later ones.
+/// Trials that weren't started by this tuner (e.g., loaded ones) are handled by the current stage.
+#[derive(Debug)]
+pub struct ChainTuner {
+    stages: Vec<Stage>,
+    current: usize,
+    owners: HashMap<TrialId, usize>,
+    starting: Option<usize>,
+}
+
+impl ChainTuner {
+    fn new(stages: Vec<Stage>) -> Self {
+        let mut this = Self {
+            stages,
+            current: 0,
+            owners: HashMap::new(),
+            starting: None,
+        };
+        this.advance();
+        this
+    }
+
+    fn advance(&mut self) {
+        while self.current + 1 < self.stages.len() && self.stages[self.current].is_exhausted() {
+            self.current += 1;
+        }
+    }
+
+    fn owner(&mut self, trial_id: TrialId) -> usize {
+        *self.owners.entry(trial_id).or_insert(self.current)
+    }
+
+    // If `draining` is `true`, only the actions for the trials owned by the stage are returned.
+    fn next_stage_action(&mut self, i: usize, draining: bool) -> Option<Option<Action>> {
+        loop {
+            let action = self.stages[i].tuner.next_action();
+            match action {
+                Some(Action::ResumeTrial { trial_id })
+                | Some(Action::FinishTrial { trial_id })
+                | Some(Action::CopyTrialDir { to: trial_id, .. })
+                    if self.owners.get(&trial_id) != Some(&i) =>
+                {
+                    // The trial is owned by an earlier stage.
+                    continue;
+                }
+                Some(Action::FinishTrial { trial_id }) => {
+                    self.owners.remove(&trial_id);
+                    return Some(action);
+                }
+                Some(Action::ResumeTrial { .. }) | Some(Action::CopyTrialDir { .. }) => {
+                    return Some(action)
+                }
+                _ if draining => return None,
+                _ => return Some(action),
+            }
+        }
+    }
+}
+
+impl Tune for ChainTuner {
+    fn ask(
+        &mut self,
+        obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        let i = self.owner(obs.trial_id);
+        self.stages[i].tuner.ask(obs, param_name, param_type)
+    }
+
+    fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        let i = self.owner(obs.trial_id);
+        // The actions of the later stages for the trial (e.g., retrying a failure) are discarded
+        // by `next_stage_action` because they don't own it.
+        for stage in &mut self.stages[i..] {
+            stage.tuner.tell(obs)?;
+        }
+        Ok(())
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        for i in 0..self.current {
+            if let Some(action) = self.next_stage_action(i, true) {
+                return action;
+            }
+        }
+
+        let i = self.current;
+        if !self.stages[i].is_exhausted() {
+            let action = self.next_stage_action(i, false).flatten();
+            if matches!(action, Some(Action::QuitOptimization)) && i + 1 < self.stages.len() {
+                // Only the stage finishes, and its running trials are drained.
+                self.stages[i].finished = true;
+                self.advance();
+                return self.next_action();
+            }
+            if action.is_none() {
+                self.starting = Some(i);
+            }
+            action
+        } else if let Some(action) = self.next_stage_action(i, true) {
+            action
+        } else if self.owners.is_empty() {
+            Some(Action::QuitOptimization)
+        } else {
+            Some(Action::WaitObservations)
+        }
+    }
+
+    fn start_trial(&mut self, trial_id: TrialId) {
+        let i = self.starting.take().unwrap_or(self.current);
+        self.owners.insert(trial_id, i);
+        self.stages[i].started_trials += 1;
+        self.stages[i].tuner.start_trial(trial_id);
+        self.advance();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::ObservationId;
+    use crate::tuners::pbt::PbtTunerSpec;
+    use std::num::NonZeroUsize;
+
+    fn chain(budgets: &[Option<usize>]) -> ChainTuner {
+        let stages = budgets
+            .iter()
+            .map(|&trials| ChainStageSpec {
+                tuner: TunerSpec::default(),
+                trials,
+            })
+            .collect();
+        ChainTunerSpec { stages }.build().unwrap()
+    }
+
+    fn succeeded(obs_id: u64, trial_id: u64) -> Observation {
+        let mut obs = Observation::new(ObservationId::new(obs_id), TrialId::new(trial_id));
+        obs.exit_status = Some(0);
+        obs
+    }
+
+    #[test]
+    fn stages_own_the_trials_they_start() -> anyhow::Result<()> {
+        let mut tuner = chain(&[Some(1), None]);
+
+        assert!(tuner.next_action().is_none());
+        // A loaded trial told between `next_action` and `start_trial`.
+        tuner.tell(&succeeded(100, 100))?;
+        tuner.start_trial(TrialId::new(0));
+        assert_eq!(tuner.owners[&TrialId::new(0)], 0);
+        assert_eq!(tuner.current, 1);
+
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::FinishTrial { trial_id }) if trial_id == TrialId::new(100)
+        ));
+        assert!(tuner.next_action().is_none());
+        tuner.start_trial(TrialId::new(1));
+        assert_eq!(tuner.owners[&TrialId::new(1)], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn quitting_stage_switches_to_the_next_one() -> anyhow::Result<()> {
+        let pbt = PbtTunerSpec {
+            seed: Some("0".parse()?),
+            population: NonZeroUsize::new(1).expect("non-zero"),
+            quantile: 0.25,
+            resample_probability: 0.25,
+            metric: None,
+        };
+        let stages = vec![
+            Stage {
+                tuner: Tuner::new(pbt.build()?),
+                budget: None,
+                started_trials: 0,
+                finished: false,
+            },
+            Stage {
+                tuner: TunerSpec::default().build()?,
+                budget: None,
+                started_trials: 0,
+                finished: false,
+            },
+        ];
+        let mut tuner = ChainTuner::new(stages);
+
+        assert!(tuner.next_action().is_none());
+        tuner.start_trial(TrialId::new(0));
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::WaitObservations)
+        ));
+
+        // The PBT population has finished, so the stage quits.
+        let mut failed = succeeded(0, 0);
+        failed.exit_status = Some(1);
+        tuner.tell(&failed)?;
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::FinishTrial { trial_id }) if trial_id == TrialId::new(0)
+        ));
+        assert!(tuner.next_action().is_none());
+        tuner.start_trial(TrialId::new(1));
+        assert_eq!(tuner.current, 1);
+        assert_eq!(tuner.owners[&TrialId::new(1)], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn quits_after_the_last_stage_finishes() -> anyhow::Result<()> {
+        let mut tuner = chain(&[Some(1), Some(1)]);
+        for trial_id in 0..2 {
+            assert!(tuner.next_action().is_none());
+            tuner.start_trial(TrialId::new(trial_id));
+        }
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::WaitObservations)
+        ));
+
+        for trial_id in 0..2 {
+            tuner.tell(&succeeded(trial_id, trial_id))?;
+            assert!(matches!(
+                tuner.next_action(),
+                Some(Action::FinishTrial { .. })
+            ));
+        }
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::QuitOptimization)
+        ));
+        Ok(())
+    }
+}
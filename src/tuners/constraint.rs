@@ -0,0 +1,634 @@
+use crate::param::{ParamName, ParamType, ParamValue};
+use crate::trial::{Observation, ObservationId, TrialId};
+use crate::tuners::{Action, Tune, Tuner};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+
+/// The maximum number of values asked to the inner tuner for a parameter until the constraints are satisfied.
+pub const MAX_ATTEMPTS: usize = 1000;
+
+/// The error returned when no value of a parameter satisfies the constraints,
+/// which fails only the observation asking the parameter.
+#[derive(Debug, thiserror::Error)]
+#[error("the constraint {constraint:?} isn't satisfied by any value of {param:?} after {MAX_ATTEMPTS} attempts")]
+pub struct UnsatisfiableConstraint {
+    constraint: String,
+    param: String,
+}
+
+/// A constraint between numeric parameters such as `warmup_steps < total_steps` or `a + b <= 1`.
+///
+/// Both sides are arithmetic expressions of numbers, parameter names, `+`, `-`, `*`, `/` and parentheses.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ParamConstraint {
+    source: String,
+    lhs: Expr,
+    op: CompareOp,
+    rhs: Expr,
+}
+
+impl ParamConstraint {
+    /// Returns the names of the parameters appearing in the constraint.
+    pub fn params(&self) -> Vec<&ParamName> {
+        let mut names = Vec::new();
+        self.lhs.collect_params(&mut names);
+        self.rhs.collect_params(&mut names);
+        names
+    }
+
+    /// Returns an error if `name` appears in the constraint but `ty` has non-numeric values.
+    pub fn check_param_type(&self, name: &ParamName, ty: &ParamType) -> anyhow::Result<()> {
+        let is_numeric = match ty {
+            ParamType::Num(_) => true,
+            ParamType::Str(t) => t.choices().get().iter().all(|c| c.parse::<f64>().is_ok()),
+            ParamType::List(_) => false,
+        };
+        anyhow::ensure!(
+            is_numeric || !self.params().contains(&name),
+            "the parameter {:?} in the constraint {:?} isn't numeric",
+            name.get(),
+            self.source
+        );
+        Ok(())
+    }
+
+    /// Evaluates the constraint, or returns `None` if some of the parameters haven't been asked yet.
+    ///
+    /// A side that isn't a finite number (e.g., division by zero) never satisfies the constraint.
+    pub fn is_satisfied<'a, F>(&self, lookup: F) -> anyhow::Result<Option<bool>>
+    where
+        F: Fn(&ParamName) -> Option<&'a ParamValue>,
+    {
+        let (lhs, rhs) = match (self.lhs.eval(&lookup)?, self.rhs.eval(&lookup)?) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => return Ok(None),
+        };
+        if !lhs.is_finite() || !rhs.is_finite() {
+            return Ok(Some(false));
+        }
+        Ok(Some(match self.op {
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Ge => lhs >= rhs,
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Eq => lhs == rhs,
+            CompareOp::Ne => lhs != rhs,
+        }))
+    }
+}
+
+impl std::fmt::Display for ParamConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::str::FromStr for ParamConstraint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s)?;
+        let lhs = parser.expr()?;
+        let op = match parser.next() {
+            Some(Token::Op(op)) => op,
+            _ => anyhow::bail!(
+                "No comparison operator (`<=`, `>=`, `<`, `>`, `==` or `!=`) in a constraint: {:?}",
+                s
+            ),
+        };
+        let rhs = parser.expr()?;
+        if let Some(token) = parser.next() {
+            anyhow::bail!("Unexpected token {:?} in a constraint: {:?}", token, s);
+        }
+        Ok(Self {
+            source: s.to_owned(),
+            lhs,
+            op,
+            rhs,
+        })
+    }
+}
+
+impl TryFrom<String> for ParamConstraint {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ParamConstraint> for String {
+    fn from(c: ParamConstraint) -> Self {
+        c.source
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Le,
+    Ge,
+    Lt,
+    Gt,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(f64),
+    Param(ParamName),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn collect_params<'a>(&'a self, names: &mut Vec<&'a ParamName>) {
+        match self {
+            Self::Num(_) => {}
+            Self::Param(name) => {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            Self::Neg(x) => x.collect_params(names),
+            Self::Add(x, y) | Self::Sub(x, y) | Self::Mul(x, y) | Self::Div(x, y) => {
+                x.collect_params(names);
+                y.collect_params(names);
+            }
+        }
+    }
+
+    fn eval<'a, F>(&self, lookup: &F) -> anyhow::Result<Option<f64>>
+    where
+        F: Fn(&ParamName) -> Option<&'a ParamValue>,
+    {
+        let binary = |x: &Expr, y: &Expr, f: fn(f64, f64) -> f64| -> anyhow::Result<Option<f64>> {
+            Ok(match (x.eval(lookup)?, y.eval(lookup)?) {
+                (Some(x), Some(y)) => Some(f(x, y)),
+                _ => None,
+            })
+        };
+        match self {
+            Self::Num(v) => Ok(Some(*v)),
+            Self::Param(name) => {
+                let value = if let Some(value) = lookup(name) {
+                    value
+                } else {
+                    return Ok(None);
+                };
+                let v = match value {
+                    ParamValue::Str(v) => v.parse().ok(),
+                    v => v.as_f64(),
+                };
+                v.map(Some).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "the value {:?} of the parameter {:?} isn't a number",
+                        value.to_string(),
+                        name.get()
+                    )
+                })
+            }
+            Self::Neg(x) => Ok(x.eval(lookup)?.map(|x| -x)),
+            Self::Add(x, y) => binary(x, y, |x, y| x + y),
+            Self::Sub(x, y) => binary(x, y, |x, y| x - y),
+            Self::Mul(x, y) => binary(x, y, |x, y| x * y),
+            Self::Div(x, y) => binary(x, y, |x, y| x / y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(CompareOp),
+    Symbol(char),
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn new(s: &str) -> anyhow::Result<Self> {
+        let mut tokens = Vec::new();
+        let mut chars = s.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c.is_ascii_digit() || c == '.' {
+                let mut n = String::new();
+                while let Some(&c) = chars.peek() {
+                    let is_exp_sign = (c == '+' || c == '-') && n.ends_with(['e', 'E']);
+                    if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || is_exp_sign {
+                        n.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let v = n
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid number {:?} in a constraint", n))?;
+                tokens.push(Token::Num(v));
+            } else if c.is_alphabetic() || c == '_' {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '.' {
+                        name.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(name));
+            } else if "<>=!".contains(c) {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                let op = match (c, eq) {
+                    ('<', true) => CompareOp::Le,
+                    ('>', true) => CompareOp::Ge,
+                    ('<', false) => CompareOp::Lt,
+                    ('>', false) => CompareOp::Gt,
+                    ('=', true) => CompareOp::Eq,
+                    ('!', true) => CompareOp::Ne,
+                    _ => anyhow::bail!("Unknown operator {:?} in a constraint", c),
+                };
+                tokens.push(Token::Op(op));
+            } else if "+-*/()".contains(c) {
+                chars.next();
+                tokens.push(Token::Symbol(c));
+            } else {
+                anyhow::bail!("Unexpected character {:?} in a constraint", c);
+            }
+        }
+        Ok(Self {
+            tokens: tokens.into_iter().peekable(),
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.tokens.next()
+    }
+
+    fn next_if_symbol(&mut self, symbols: &str) -> Option<char> {
+        match self.tokens.peek() {
+            Some(Token::Symbol(c)) if symbols.contains(*c) => {
+                let c = *c;
+                self.tokens.next();
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> anyhow::Result<Expr> {
+        let mut x = self.term()?;
+        while let Some(c) = self.next_if_symbol("+-") {
+            let y = Box::new(self.term()?);
+            x = if c == '+' {
+                Expr::Add(Box::new(x), y)
+            } else {
+                Expr::Sub(Box::new(x), y)
+            };
+        }
+        Ok(x)
+    }
+
+    fn term(&mut self) -> anyhow::Result<Expr> {
+        let mut x = self.factor()?;
+        while let Some(c) = self.next_if_symbol("*/") {
+            let y = Box::new(self.factor()?);
+            x = if c == '*' {
+                Expr::Mul(Box::new(x), y)
+            } else {
+                Expr::Div(Box::new(x), y)
+            };
+        }
+        Ok(x)
+    }
+
+    fn factor(&mut self) -> anyhow::Result<Expr> {
+        match self.next() {
+            Some(Token::Num(v)) => Ok(Expr::Num(v)),
+            Some(Token::Ident(name)) => Ok(Expr::Param(ParamName::new(name))),
+            Some(Token::Symbol('-')) => Ok(Expr::Neg(Box::new(self.factor()?))),
+            Some(Token::Symbol('(')) => {
+                let x = self.expr()?;
+                anyhow::ensure!(
+                    self.next() == Some(Token::Symbol(')')),
+                    "Unclosed parenthesis in a constraint"
+                );
+                Ok(x)
+            }
+            Some(token) => anyhow::bail!("Unexpected token {:?} in a constraint", token),
+            None => anyhow::bail!("Unexpected end of a constraint"),
+        }
+    }
+}
+
+/// A tuner that re-asks the inner tuner until the value satisfies all the constraints.
+///
+/// A constraint is checked when the last of its parameters is asked.
+/// The parameters of the constraint that haven't been asked yet are resampled together
+/// (once their types are known from earlier asks), and the chosen values are returned when they are asked.
+/// Deterministic tuners (e.g., `sobol` or `pbt`) propose the same value again, so they can't be used with constraints.
+#[derive(Debug)]
+pub struct ConstraintTuner {
+    tuner: Tuner,
+    constraints: Vec<ParamConstraint>,
+    param_types: HashMap<ParamName, ParamType>,
+    presampled: HashMap<ObservationId, BTreeMap<ParamName, ParamValue>>,
+    warned_params: HashSet<ParamName>,
+}
+
+impl ConstraintTuner {
+    pub fn new(tuner: Tuner, constraints: Vec<ParamConstraint>) -> Self {
+        Self {
+            tuner,
+            constraints,
+            param_types: HashMap::new(),
+            presampled: HashMap::new(),
+            warned_params: HashSet::new(),
+        }
+    }
+
+    /// Returns the not-yet-asked parameters of the constraints on `param_name` whose types are known.
+    fn unasked_params(
+        &self,
+        obs: &Observation,
+        param_name: &ParamName,
+    ) -> Vec<(ParamName, ParamType)> {
+        let mut params = Vec::new();
+        for constraint in &self.constraints {
+            let names = constraint.params();
+            if !names.contains(&param_name) {
+                continue;
+            }
+            for name in names {
+                if name == param_name
+                    || obs.params.contains_key(name)
+                    || params.iter().any(|(n, _)| n == name)
+                {
+                    continue;
+                }
+                if let Some(ty) = self.param_types.get(name) {
+                    params.push((name.clone(), ty.clone()));
+                }
+            }
+        }
+        params
+    }
+
+    fn check(
+        &self,
+        obs: &Observation,
+        candidate: &BTreeMap<ParamName, ParamValue>,
+    ) -> anyhow::Result<Option<&ParamConstraint>> {
+        let presampled = self.presampled.get(&obs.id);
+        let lookup = |name: &ParamName| {
+            candidate
+                .get(name)
+                .or_else(|| obs.params.get(name).map(|p| &p.value))
+                .or_else(|| presampled.and_then(|p| p.get(name)))
+        };
+        for constraint in &self.constraints {
+            if constraint.is_satisfied(lookup)? == Some(false) {
+                return Ok(Some(constraint));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Tune for ConstraintTuner {
+    fn ask(
+        &mut self,
+        obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        self.param_types
+            .insert(param_name.clone(), param_type.clone());
+        if let Some(value) = self
+            .presampled
+            .get_mut(&obs.id)
+            .and_then(|p| p.remove(param_name))
+            .and_then(|v| param_type.normalize(&v).ok())
+        {
+            return Ok(value);
+        }
+
+        let is_constrained = self
+            .constraints
+            .iter()
+            .any(|c| c.params().contains(&param_name));
+        if !is_constrained {
+            return self.tuner.ask(obs, param_name, param_type);
+        }
+        for constraint in &self.constraints {
+            constraint.check_param_type(param_name, param_type)?;
+        }
+
+        let unasked = self.unasked_params(obs, param_name);
+        let mut violated = None;
+        for _ in 0..MAX_ATTEMPTS {
+            let mut candidate = BTreeMap::new();
+            let value = self.tuner.ask(obs, param_name, param_type)?;
+            candidate.insert(param_name.clone(), value.clone());
+            for (name, ty) in &unasked {
+                candidate.insert(name.clone(), self.tuner.ask(obs, name, ty)?);
+            }
+            violated = self.check(obs, &candidate)?.map(|c| c.to_string());
+            if violated.is_none() {
+                candidate.remove(param_name);
+                if !candidate.is_empty() {
+                    self.presampled.entry(obs.id).or_default().extend(candidate);
+                }
+                return Ok(value);
+            }
+        }
+        Err(UnsatisfiableConstraint {
+            constraint: violated.unwrap_or_default(),
+            param: param_name.get().to_owned(),
+        }
+        .into())
+    }
+
+    fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        self.presampled.remove(&obs.id);
+        if obs.is_succeeded() {
+            for constraint in &self.constraints {
+                for name in constraint.params() {
+                    if !obs.params.contains_key(name) && self.warned_params.insert(name.clone()) {
+                        eprintln!(
+                            "warning: the parameter {:?} in the constraint {:?} wasn't asked by the observation {}, so the constraint isn't checked",
+                            name.get(),
+                            constraint.to_string(),
+                            obs.id.get()
+                        );
+                    }
+                }
+            }
+        }
+        self.tuner.tell(obs)
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        self.tuner.next_action()
+    }
+
+    fn start_trial(&mut self, trial_id: TrialId) {
+        self.tuner.start_trial(trial_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{
+        CategoricalParamType, DiscreteParamType, NumParamType, ParamInstance, StrParamType,
+    };
+    use crate::tuners::random::RandomTunerSpec;
+    use crate::types::FiniteF64;
+
+    fn num(v: f64) -> ParamValue {
+        ParamValue::Num(FiniteF64::new(v).expect("finite"))
+    }
+
+    fn eval(constraint: &str, params: &[(&str, f64)]) -> anyhow::Result<Option<bool>> {
+        let params = params
+            .iter()
+            .map(|(k, v)| (ParamName::new((*k).to_owned()), num(*v)))
+            .collect::<BTreeMap<_, _>>();
+        constraint
+            .parse::<ParamConstraint>()?
+            .is_satisfied(|name| params.get(name))
+    }
+
+    #[test]
+    fn operators_follow_precedence() -> anyhow::Result<()> {
+        assert_eq!(eval("1 + 2 * 3 == 7", &[])?, Some(true));
+        assert_eq!(eval("(1 + 2) * 3 == 9", &[])?, Some(true));
+        assert_eq!(eval("10 - 4 - 3 == 3", &[])?, Some(true));
+        assert_eq!(eval("8 / 4 / 2 == 1", &[])?, Some(true));
+        assert_eq!(
+            eval("a + b * c < 10", &[("a", 1.0), ("b", 2.0), ("c", 4.0)])?,
+            Some(true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unary_minus() -> anyhow::Result<()> {
+        assert_eq!(eval("-2 * -3 == 6", &[])?, Some(true));
+        assert_eq!(eval("- -1 == 1", &[])?, Some(true));
+        assert_eq!(eval("-(1 + 2) == -3", &[])?, Some(true));
+        assert_eq!(eval("a - -b == 3", &[("a", 1.0), ("b", 2.0)])?, Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn numbers_with_exponents() -> anyhow::Result<()> {
+        assert_eq!(eval("1e3 == 1000", &[])?, Some(true));
+        assert_eq!(eval("2.5E-2 == 0.025", &[])?, Some(true));
+        assert_eq!(eval("1e+2 - 1 == 99", &[])?, Some(true));
+        assert!("1e < 2".parse::<ParamConstraint>().is_err());
+        assert!("1.2.3 < 2".parse::<ParamConstraint>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reject_malformed_constraints() {
+        for s in &[
+            "a < b c",
+            "a < b )",
+            "(a < b",
+            "a b",
+            "a",
+            "a <",
+            "a < b < c",
+            "a = b",
+            "a % b > 0",
+        ] {
+            assert!(s.parse::<ParamConstraint>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn division_by_zero_violates_constraints() -> anyhow::Result<()> {
+        assert_eq!(eval("1 / a >= 0", &[("a", 0.0)])?, Some(false));
+        assert_eq!(eval("a / b != 1", &[("a", 0.0), ("b", 0.0)])?, Some(false));
+        assert_eq!(eval("1 / a >= 0", &[("a", 2.0)])?, Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn unasked_params_are_not_evaluated() -> anyhow::Result<()> {
+        assert_eq!(eval("a < b", &[("a", 1.0)])?, None);
+        let c: ParamConstraint = "a + b * a <= c".parse()?;
+        let names = c.params().into_iter().map(|n| n.get()).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn non_numeric_params_are_rejected() -> anyhow::Result<()> {
+        let inner = RandomTunerSpec {
+            seed: Some("0".parse()?),
+        }
+        .build()?;
+        let mut tuner = ConstraintTuner::new(Tuner::new(inner), vec!["a < b".parse()?]);
+        let choices = |choices: &[&str]| -> anyhow::Result<ParamType> {
+            let choices = choices.iter().map(|&c| c.to_owned()).collect();
+            let ty = CategoricalParamType::new(choices, None)?;
+            Ok(ParamType::Str(StrParamType::Categorical(ty)))
+        };
+        let obs = Observation::new(ObservationId::new(0), TrialId::new(0));
+
+        let a = ParamName::new("a".to_owned());
+        assert!(tuner.ask(&obs, &a, &choices(&["0", "1"])?).is_ok());
+        let b = ParamName::new("b".to_owned());
+        let e = tuner
+            .ask(&obs, &b, &choices(&["adam", "sgd"])?)
+            .expect_err("non-numeric");
+        assert!(!e.is::<UnsatisfiableConstraint>());
+        Ok(())
+    }
+
+    #[test]
+    fn unasked_params_are_resampled_together() -> anyhow::Result<()> {
+        let inner = RandomTunerSpec {
+            seed: Some("0".parse()?),
+        }
+        .build()?;
+        let mut tuner = ConstraintTuner::new(Tuner::new(inner), vec!["a < b".parse()?]);
+        let ty = ParamType::Num(NumParamType::Discrete(DiscreteParamType::new(
+            0.0, 3.0, 1.0, false, None,
+        )?));
+        let (a, b) = (
+            ParamName::new("a".to_owned()),
+            ParamName::new("b".to_owned()),
+        );
+
+        // Only `b` can be resampled until the type of `b` is known,
+        // but afterwards `a` is never the max, which has no greater value of `b`.
+        let mut obs = Observation::new(ObservationId::new(0), TrialId::new(0));
+        obs.insert_param(a.clone(), ParamInstance::new(ty.clone(), num(0.0)));
+        tuner.ask(&obs, &b, &ty)?;
+
+        for i in 1..1000 {
+            let mut obs = Observation::new(ObservationId::new(i), TrialId::new(i));
+            let va = tuner.ask(&obs, &a, &ty)?;
+            obs.insert_param(a.clone(), ParamInstance::new(ty.clone(), va.clone()));
+            let vb = tuner.ask(&obs, &b, &ty)?;
+            assert!(va.as_f64() < vb.as_f64(), "{} < {}", va, vb);
+            tuner.tell(&obs)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::param::{ParamName, ParamType, ParamValue};
+use crate::trial::{Observation, TrialId};
+use crate::tuners::{Action, Tune, Tuner};
+use anyhow::Context;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::BufRead;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EnqueuedParams(BTreeMap<ParamName, ParamValue>);
+
+impl EnqueuedParams {
+    pub fn load_jsonl<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<Self>> {
+        let file = std::fs::File::open(path)?;
+        let mut params = Vec::new();
+        for (i, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let p = line
+                .parse()
+                .with_context(|| format!("invalid parameters at line {}", i + 1))?;
+            params.push(p);
+        }
+        Ok(params)
+    }
+
+    pub fn get(&self, name: &ParamName) -> Option<&ParamValue> {
+        self.0.get(name)
+    }
+}
+
+impl std::str::FromStr for EnqueuedParams {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::json::parse_json(s)
+    }
+}
+
+#[derive(Debug)]
+pub struct EnqueueTuner {
+    tuner: Tuner,
+    queue: VecDeque<EnqueuedParams>,
+    assigned: HashMap<TrialId, EnqueuedParams>,
+    visited: HashSet<TrialId>,
+}
+
+impl EnqueueTuner {
+    pub fn new(tuner: Tuner, queue: Vec<EnqueuedParams>) -> Self {
+        Self {
+            tuner,
+            queue: queue.into(),
+            assigned: HashMap::new(),
+            visited: HashSet::new(),
+        }
+    }
+}
+
+impl Tune for EnqueueTuner {
+    fn ask(
+        &mut self,
+        obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        if self.visited.insert(obs.trial_id) {
+            if let Some(params) = self.queue.pop_front() {
+                self.assigned.insert(obs.trial_id, params);
+            }
+        }
+
+        if let Some(value) = self
+            .assigned
+            .get(&obs.trial_id)
+            .and_then(|params| params.get(param_name))
+        {
+            param_type.normalize(value).with_context(|| {
+                format!(
+                    "the enqueued value of {:?} doesn't match the parameter type",
+                    param_name.get()
+                )
+            })
+        } else {
+            self.tuner.ask(obs, param_name, param_type)
+        }
+    }
+
+    fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        self.tuner.tell(obs)
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        let action = self.tuner.next_action();
+        if let Some(Action::FinishTrial { trial_id }) = action {
+            self.assigned.remove(&trial_id);
+            self.visited.remove(&trial_id);
+        }
+        action
+    }
+
+    fn start_trial(&mut self, trial_id: TrialId) {
+        self.tuner.start_trial(trial_id)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::param::{ParamName, ParamType, ParamValue};
+use crate::trial::{Observation, TrialId};
+use crate::tuners::{Action, Tune, Tuner};
+use crate::types::FiniteF64;
+use anyhow::Context;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct FixedParam {
+    pub name: ParamName,
+    pub value: ParamValue,
+}
+
+impl std::str::FromStr for FixedParam {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut iter = s.splitn(2, '=');
+        let name = iter.next().expect("unreachable");
+        let value = iter
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No value part in a fixed parameter: {:?}", s))?;
+        let value = match value.parse().ok().and_then(|v| FiniteF64::new(v).ok()) {
+            Some(v) => ParamValue::Num(v),
+            None => ParamValue::Str(value.to_owned()),
+        };
+        Ok(Self {
+            name: ParamName::new(name.to_owned()),
+            value,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct FixTuner {
+    tuner: Tuner,
+    fixed: BTreeMap<ParamName, ParamValue>,
+}
+
+impl FixTuner {
+    pub fn new(tuner: Tuner, fixed: BTreeMap<ParamName, ParamValue>) -> Self {
+        Self { tuner, fixed }
+    }
+}
+
+impl Tune for FixTuner {
+    fn ask(
+        &mut self,
+        obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        if let Some(value) = self.fixed.get(param_name) {
+            param_type.normalize(value).with_context(|| {
+                format!(
+                    "the fixed value of {:?} doesn't match the parameter type",
+                    param_name.get()
+                )
+            })
+        } else {
+            self.tuner.ask(obs, param_name, param_type)
+        }
+    }
+
+    fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        self.tuner.tell(obs)
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        self.tuner.next_action()
+    }
+
+    fn start_trial(&mut self, trial_id: TrialId) {
+        self.tuner.start_trial(trial_id)
+    }
+}
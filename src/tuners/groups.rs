@@ -0,0 +1,143 @@
+use crate::param::{ParamName, ParamType};
+use crate::trial::Observation;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Observations grouped by the set of parameters that were active (asked) in them.
+///
+/// Scripts may ask different parameters depending on earlier answers,
+/// so model-based tuners should only build a model for a parameter from the observations
+/// that share the parameters asked so far.
+#[derive(Debug, Default)]
+pub struct ObservationGroups {
+    groups: BTreeMap<BTreeSet<ParamName>, Vec<Observation>>,
+}
+
+impl ObservationGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, obs: Observation) {
+        self.groups.entry(obs.param_names()).or_default().push(obs);
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = (&BTreeSet<ParamName>, &[Observation])> {
+        self.groups.iter().map(|(k, v)| (k, v.as_slice()))
+    }
+
+    /// Returns the observations that asked `param_name` and all the parameters asked so far in `obs`,
+    /// and that took the same values for the categorical ones among them.
+    pub fn relevant<'a>(
+        &'a self,
+        obs: &'a Observation,
+        param_name: &'a ParamName,
+    ) -> impl 'a + Iterator<Item = &'a Observation> {
+        self.groups
+            .iter()
+            .filter(move |(names, _)| {
+                names.contains(param_name) && obs.params.keys().all(|n| names.contains(n))
+            })
+            .flat_map(|(_, observations)| observations.iter())
+            .filter(move |o| {
+                obs.params
+                    .iter()
+                    .filter(|(_, p)| matches!(p.ty, ParamType::Str(_)))
+                    .all(|(name, p)| o.params.get(name).map(|q| &q.value) == Some(&p.value))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{
+        CategoricalParamType, ContinousParamType, NumParamType, ParamInstance, ParamValue,
+        StrParamType,
+    };
+    use crate::trial::{ObservationId, TrialId};
+    use crate::types::FiniteF64;
+
+    fn name(s: &str) -> ParamName {
+        ParamName::new(s.to_owned())
+    }
+
+    fn optimizer(value: &str) -> ParamInstance {
+        let ty = CategoricalParamType::new(vec!["adam".to_owned(), "sgd".to_owned()], None)
+            .expect("valid");
+        ParamInstance::new(
+            ParamType::Str(StrParamType::Categorical(ty)),
+            ParamValue::Str(value.to_owned()),
+        )
+    }
+
+    fn num(value: f64) -> ParamInstance {
+        let ty = ContinousParamType::new(0.0, 1.0, false, None).expect("valid");
+        ParamInstance::new(
+            ParamType::Num(NumParamType::Continous(ty)),
+            ParamValue::Num(FiniteF64::new(value).expect("finite")),
+        )
+    }
+
+    // `adam` asks `beta1` and `sgd` asks `momentum` after `lr`.
+    fn obs(id: u64, opt: &str, lr: f64, x: f64) -> Observation {
+        let mut obs = Observation::new(ObservationId::new(id), TrialId::new(id));
+        obs.insert_param(name("optimizer"), optimizer(opt));
+        obs.insert_param(name("lr"), num(lr));
+        let conditional = if opt == "adam" { "beta1" } else { "momentum" };
+        obs.insert_param(name(conditional), num(x));
+        obs
+    }
+
+    fn groups() -> ObservationGroups {
+        let mut groups = ObservationGroups::new();
+        groups.insert(obs(0, "adam", 0.1, 0.9));
+        groups.insert(obs(1, "sgd", 0.2, 0.5));
+        groups.insert(obs(2, "adam", 0.3, 0.8));
+        groups
+    }
+
+    fn ids<'a>(observations: impl Iterator<Item = &'a Observation>) -> Vec<u64> {
+        let mut ids = observations.map(|o| o.id.get()).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn observations_are_grouped_by_active_params() {
+        let groups = groups();
+        let groups = groups
+            .groups()
+            .map(|(names, observations)| {
+                let names = names.iter().map(|n| n.get()).collect::<Vec<_>>();
+                (names, ids(observations.iter()))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            [
+                (vec!["beta1", "lr", "optimizer"], vec![0, 2]),
+                (vec!["lr", "momentum", "optimizer"], vec![1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn relevant_observations_share_the_asked_params_and_categorical_values() {
+        let groups = groups();
+
+        let empty = Observation::new(ObservationId::new(10), TrialId::new(10));
+        assert_eq!(ids(groups.relevant(&empty, &name("optimizer"))), [0, 1, 2]);
+        assert_eq!(ids(groups.relevant(&empty, &name("beta1"))), [0, 2]);
+
+        // Numeric values don't have to match, but categorical ones do.
+        let mut adam = Observation::new(ObservationId::new(11), TrialId::new(11));
+        adam.insert_param(name("optimizer"), optimizer("adam"));
+        adam.insert_param(name("lr"), num(0.5));
+        assert_eq!(ids(groups.relevant(&adam, &name("beta1"))), [0, 2]);
+        assert!(ids(groups.relevant(&adam, &name("momentum"))).is_empty());
+
+        let mut sgd = Observation::new(ObservationId::new(12), TrialId::new(12));
+        sgd.insert_param(name("optimizer"), optimizer("sgd"));
+        assert_eq!(ids(groups.relevant(&sgd, &name("lr"))), [1]);
+    }
+}
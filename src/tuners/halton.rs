@@ -0,0 +1,92 @@
+use crate::rng::{ArcRng, RngSeed};
+use crate::tuners::sequence::{PointSequence, SequenceTuner};
+use rand::Rng;
+
+#[derive(Debug, Clone, Default, clap::Args, serde::Serialize, serde::Deserialize)]
+pub struct HaltonTunerSpec {
+    #[clap(long)]
+    pub seed: Option<RngSeed>,
+}
+
+impl HaltonTunerSpec {
+    pub fn build(&self) -> anyhow::Result<SequenceTuner<HaltonSequence>> {
+        let rng = ArcRng::new(self.seed.unwrap_or_default());
+        Ok(SequenceTuner::new(HaltonSequence::new(rng)))
+    }
+}
+
+/// Halton sequence randomized by a random shift (Cranley-Patterson rotation) per dimension.
+#[derive(Debug)]
+pub struct HaltonSequence {
+    rng: ArcRng,
+    primes: Vec<u64>,
+    shifts: Vec<f64>,
+}
+
+impl HaltonSequence {
+    pub fn new(rng: ArcRng) -> Self {
+        Self {
+            rng,
+            primes: Vec::new(),
+            shifts: Vec::new(),
+        }
+    }
+
+    fn extend_dimensions(&mut self, dimension: usize) {
+        while self.primes.len() <= dimension {
+            let mut n = self.primes.last().map_or(2, |p| p + 1);
+            while self.primes.iter().any(|p| n % p == 0) {
+                n += 1;
+            }
+            self.primes.push(n);
+            self.shifts.push(self.rng.gen());
+        }
+    }
+}
+
+impl PointSequence for HaltonSequence {
+    fn sample(&mut self, index: u64, dimension: usize) -> anyhow::Result<f64> {
+        self.extend_dimensions(dimension);
+        let base = self.primes[dimension];
+
+        // The first point (i.e., the origin) is skipped.
+        let mut i = index + 1;
+        let mut f = 1.0;
+        let mut v = 0.0;
+        while i > 0 {
+            f /= base as f64;
+            v += f * (i % base) as f64;
+            i /= base;
+        }
+        Ok((v + self.shifts[dimension]).fract())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimensions_use_consecutive_primes() {
+        let mut halton = HaltonSequence::new(ArcRng::new("0".parse().unwrap()));
+        halton.extend_dimensions(5);
+        assert_eq!(halton.primes, [2, 3, 5, 7, 11, 13]);
+    }
+
+    #[test]
+    fn points_are_shifted_radical_inverses() -> anyhow::Result<()> {
+        let mut halton = HaltonSequence::new(ArcRng::new("0".parse()?));
+        for (dimension, expected) in [
+            (0, [0.5, 0.25, 0.75, 0.125]),
+            (1, [1.0 / 3.0, 2.0 / 3.0, 1.0 / 9.0, 4.0 / 9.0]),
+        ] {
+            for (index, expected) in expected.iter().enumerate() {
+                let u = halton.sample(index as u64, dimension)?;
+                assert!((0.0..1.0).contains(&u));
+                let v = (u - halton.shifts[dimension] + 1.0).fract();
+                assert!((v - expected).abs() < 1e-9, "{} != {}", v, expected);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::rng::{ArcRng, RngSeed};
+use crate::tuners::sequence::{PointSequence, SequenceTuner};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+#[derive(Debug, Clone, clap::Args, serde::Serialize, serde::Deserialize)]
+pub struct LhsTunerSpec {
+    #[clap(long)]
+    pub seed: Option<RngSeed>,
+
+    /// The number of trials in a Latin hypercube.
+    #[clap(long, default_value = "10")]
+    pub samples: NonZeroUsize,
+}
+
+impl LhsTunerSpec {
+    pub fn build(&self) -> anyhow::Result<SequenceTuner<LatinHypercube>> {
+        let rng = ArcRng::new(self.seed.unwrap_or_default());
+        Ok(SequenceTuner::new(LatinHypercube::new(rng, self.samples)))
+    }
+}
+
+/// Latin hypercube sampling.
+///
+/// Every `samples` consecutive points make up a Latin hypercube,
+/// i.e., each of them falls into a different stratum in every dimension.
+#[derive(Debug)]
+pub struct LatinHypercube {
+    rng: ArcRng,
+    samples: NonZeroUsize,
+    /// The permutations of the strata of the incomplete blocks, with the number of points left in each.
+    permutations: HashMap<(usize, u64), (Vec<usize>, usize)>,
+}
+
+impl LatinHypercube {
+    pub fn new(rng: ArcRng, samples: NonZeroUsize) -> Self {
+        Self {
+            rng,
+            samples,
+            permutations: HashMap::new(),
+        }
+    }
+}
+
+impl PointSequence for LatinHypercube {
+    fn sample(&mut self, index: u64, dimension: usize) -> anyhow::Result<f64> {
+        let n = self.samples.get();
+        let block = index / n as u64;
+        let rng = &mut self.rng;
+        let (permutation, remaining) =
+            self.permutations
+                .entry((dimension, block))
+                .or_insert_with(|| {
+                    let mut p = (0..n).collect::<Vec<_>>();
+                    p.shuffle(rng);
+                    (p, n)
+                });
+        let stratum = permutation[(index % n as u64) as usize];
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.permutations.remove(&(dimension, block));
+        }
+        Ok((stratum as f64 + self.rng.gen::<f64>()) / n as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_block_covers_all_strata() -> anyhow::Result<()> {
+        let samples = NonZeroUsize::new(5).unwrap();
+        let mut lhs = LatinHypercube::new(ArcRng::new("1".parse()?), samples);
+        for block in 0..3 {
+            for dimension in 0..2 {
+                let mut strata = (0..5)
+                    .map(|i| lhs.sample(block * 5 + i, dimension))
+                    .map(|u| u.map(|u| (u * 5.0).floor() as usize))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                strata.sort_unstable();
+                assert_eq!(strata, [0, 1, 2, 3, 4]);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn completed_blocks_are_dropped() -> anyhow::Result<()> {
+        let samples = NonZeroUsize::new(3).unwrap();
+        let mut lhs = LatinHypercube::new(ArcRng::new("1".parse()?), samples);
+        lhs.sample(0, 0)?;
+        lhs.sample(1, 0)?;
+        lhs.sample(3, 0)?;
+        assert_eq!(lhs.permutations.len(), 2);
+
+        lhs.sample(2, 0)?;
+        assert_eq!(lhs.permutations.len(), 1);
+        assert!(lhs.permutations.contains_key(&(0, 1)));
+        Ok(())
+    }
+}
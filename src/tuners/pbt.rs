@@ -0,0 +1,394 @@
+use crate::metric::MetricType;
+use crate::param::{NumParamType, ParamInstance, ParamName, ParamType, ParamValue, StrParamType};
+use crate::rng::{ArcRng, RngSeed};
+use crate::trial::{Observation, ObservationId, TrialId};
+use crate::tuners::random::RandomTuner;
+use crate::tuners::{Action, ActionQueue, Tune};
+use crate::types::FiniteF64;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroUsize;
+
+#[derive(Debug, Clone, clap::Args, serde::Serialize, serde::Deserialize)]
+pub struct PbtTunerSpec {
+    #[clap(long)]
+    pub seed: Option<RngSeed>,
+
+    /// The number of trials evaluated in parallel.
+    #[clap(long, default_value = "10")]
+    pub population: NonZeroUsize,
+
+    /// The fraction of the population regarded as top (or bottom) performers.
+    #[clap(long, default_value = "0.25")]
+    pub quantile: f64,
+
+    /// The probability of resampling a parameter instead of perturbing it.
+    #[clap(long, default_value = "0.25")]
+    pub resample_probability: f64,
+
+    /// The name of the metric used to rank trials (the first objective metric by default).
+    #[clap(long)]
+    pub metric: Option<String>,
+}
+
+impl PbtTunerSpec {
+    pub fn build(&self) -> anyhow::Result<PbtTuner> {
+        anyhow::ensure!(
+            0.0 < self.quantile && self.quantile <= 0.5,
+            "`quantile` must be in the range (0.0, 0.5]: {}",
+            self.quantile
+        );
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&self.resample_probability),
+            "`resample_probability` must be in the range [0.0, 1.0]: {}",
+            self.resample_probability
+        );
+        let rng = ArcRng::new(self.seed.unwrap_or_default());
+        Ok(PbtTuner {
+            spec: self.clone(),
+            random: RandomTuner::new(rng.clone()),
+            rng,
+            members: HashMap::new(),
+            started: 0,
+            actions: ActionQueue::new(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Member {
+    params: BTreeMap<ParamName, ParamInstance>,
+    step: u64,
+    score: Option<f64>,
+    last_obs: Observation,
+    running: bool,
+}
+
+impl Member {
+    fn new(trial_id: TrialId) -> Self {
+        Self {
+            params: BTreeMap::new(),
+            step: 0,
+            score: None,
+            last_obs: Observation::new(ObservationId::new(0), trial_id),
+            running: true,
+        }
+    }
+}
+
+/// Population based training.
+///
+/// Each observation of a trial trains the model for one step of the fidelity parameter,
+/// storing a checkpoint in the trial directory (i.e., `hone get tempdir --scope trial`).
+/// Once every member of the population has finished a step, the trials ranked in the bottom
+/// take over the checkpoints and the (perturbed) parameters of the top trials,
+/// and then all the members are resumed for the next step.
+#[derive(Debug)]
+pub struct PbtTuner {
+    spec: PbtTunerSpec,
+    rng: ArcRng,
+    random: RandomTuner,
+    members: HashMap<TrialId, Member>,
+    started: usize,
+    actions: ActionQueue,
+}
+
+impl PbtTuner {
+    fn score(&self, obs: &Observation) -> Option<f64> {
+        let metric = if let Some(name) = &self.spec.metric {
+            obs.metrics.iter().find(|(n, _)| n.get() == name)?.1
+        } else {
+            obs.metrics.values().find(|m| m.ty != MetricType::Record)?
+        };
+        match metric.ty {
+            MetricType::Maximize => Some(-metric.value.get()),
+            _ => Some(metric.value.get()),
+        }
+    }
+
+    /// Exploits and resumes the members if all of them have finished the current step.
+    fn next_step(&mut self) -> anyhow::Result<()> {
+        let is_idle = self.started >= self.spec.population.get()
+            && !self.members.is_empty()
+            && self.members.values().all(|m| !m.running);
+        if !is_idle {
+            return Ok(());
+        }
+
+        let mut ranking = self
+            .members
+            .iter()
+            .filter_map(|(id, m)| m.score.map(|s| (s, *id)))
+            .collect::<Vec<_>>();
+        ranking.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let n = ranking.len();
+        let k = ((n as f64 * self.spec.quantile).ceil() as usize).min(n / 2);
+        if k > 0 {
+            let top = ranking[..k].iter().map(|x| x.1).collect::<Vec<_>>();
+            for &(_, to) in &ranking[n - k..] {
+                let from = *top.choose(&mut self.rng).expect("unreachable");
+                self.exploit(from, to)?;
+            }
+        }
+
+        let mut trial_ids = self.members.keys().copied().collect::<Vec<_>>();
+        trial_ids.sort();
+        for trial_id in trial_ids {
+            self.members
+                .get_mut(&trial_id)
+                .expect("unreachable")
+                .running = true;
+            self.actions.enqueue(Action::resume_trial(trial_id));
+        }
+        Ok(())
+    }
+
+    fn exploit(&mut self, from: TrialId, to: TrialId) -> anyhow::Result<()> {
+        let (params, score) = {
+            let m = &self.members[&from];
+            (m.params.clone(), m.score)
+        };
+        let obs = self.members[&to].last_obs.clone();
+        let mut perturbed = BTreeMap::new();
+        for (name, p) in params {
+            let value = self.perturb(&obs, &name, &p)?;
+            perturbed.insert(name, ParamInstance::new(p.ty, value));
+        }
+
+        let member = self.members.get_mut(&to).expect("unreachable");
+        member.params = perturbed;
+        member.score = score;
+        self.actions.enqueue(Action::copy_trial_dir(from, to));
+        Ok(())
+    }
+
+    fn perturb(
+        &mut self,
+        obs: &Observation,
+        name: &ParamName,
+        param: &ParamInstance,
+    ) -> anyhow::Result<ParamValue> {
+        if self.rng.gen_bool(self.spec.resample_probability) {
+            return self.random.ask(obs, name, &param.ty);
+        }
+
+        let up: bool = self.rng.gen();
+        let factor = if up { 1.2 } else { 0.8 };
+        let value = match (&param.ty, &param.value) {
+            (ParamType::Str(StrParamType::Ordinal(t)), ParamValue::Str(v)) => {
+                let choices = t.choices().get();
+                let i = choices.iter().position(|c| c == v).unwrap_or(0);
+                let i = if up {
+                    (i + 1).min(choices.len() - 1)
+                } else {
+                    i.saturating_sub(1)
+                };
+                ParamValue::Str(choices[i].clone())
+            }
+            (ParamType::Num(NumParamType::Continous(t)), ParamValue::Num(v)) => {
+                let v = (v.get() * factor).clamp(t.range().min().get(), t.range().max().get());
+                ParamValue::Num(FiniteF64::new(v)?)
+            }
+            (ParamType::Num(NumParamType::Discrete(t)), ParamValue::Num(v)) => {
+                let delta = if up { t.step().get() } else { -t.step().get() };
+                let v = if t.range().contains(FiniteF64::new(v.get() + delta)?) {
+                    v.get() + delta
+                } else {
+                    v.get()
+                };
+                ParamValue::Num(FiniteF64::new(v)?)
+            }
+            (ParamType::Num(NumParamType::Int(t)), ParamValue::Int(v)) => {
+                let w = (*v as f64 * factor).round() as i64;
+                let w = if w != *v {
+                    w
+                } else if up {
+                    v + 1
+                } else {
+                    v - 1
+                };
+                ParamValue::Int(w.clamp(t.min(), t.max()))
+            }
+            (ParamType::Num(NumParamType::Normal(_)), ParamValue::Num(v)) => {
+                ParamValue::Num(FiniteF64::new(v.get() * factor)?)
+            }
+            _ => param.value.clone(),
+        };
+        Ok(value)
+    }
+}
+
+impl Tune for PbtTuner {
+    fn ask(
+        &mut self,
+        obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        let member = if let Some(member) = self.members.get_mut(&obs.trial_id) {
+            member
+        } else {
+            // Not started by this tuner (e.g., loaded from another study).
+            return self.random.ask(obs, param_name, param_type);
+        };
+
+        if let ParamType::Num(NumParamType::Fidelity(t)) = param_type {
+            let step = t.step().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "PBT requires `--step` for the fidelity parameter {:?}",
+                    param_name.get()
+                )
+            })?;
+            let (min, max) = (t.range().min().get(), t.range().max().get());
+            let v = min + step.get() * member.step as f64;
+            let v = if v >= max - step.get() * 1e-6 { max } else { v };
+            return Ok(ParamValue::Num(FiniteF64::new(v)?));
+        }
+
+        if let Some(p) = member.params.get(param_name) {
+            return Ok(p.value.clone());
+        }
+        let value = self.random.ask(obs, param_name, param_type)?;
+        self.members
+            .get_mut(&obs.trial_id)
+            .expect("unreachable")
+            .params
+            .insert(
+                param_name.clone(),
+                ParamInstance::new(param_type.clone(), value.clone()),
+            );
+        Ok(value)
+    }
+
+    fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        if !self.members.contains_key(&obs.trial_id) || !obs.is_succeeded() || obs.is_max_fidelity()
+        {
+            self.members.remove(&obs.trial_id);
+            self.actions.enqueue(Action::finish_trial(obs.trial_id));
+            return self.next_step();
+        }
+
+        let score = self.score(obs);
+        let member = self.members.get_mut(&obs.trial_id).expect("unreachable");
+        member.step += 1;
+        member.score = score;
+        member.last_obs = obs.clone();
+        member.running = false;
+        self.next_step()
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        if let Some(action) = self.actions.next() {
+            Some(action)
+        } else if self.started < self.spec.population.get() {
+            self.started += 1;
+            None
+        } else if self.members.is_empty() {
+            Some(Action::QuitOptimization)
+        } else {
+            Some(Action::WaitObservations)
+        }
+    }
+
+    fn start_trial(&mut self, trial_id: TrialId) {
+        self.members.insert(trial_id, Member::new(trial_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{MetricInstance, MetricName, MetricValue};
+    use crate::param::FidelityParamType;
+
+    fn pbt(population: usize) -> PbtTuner {
+        PbtTunerSpec {
+            seed: Some("0".parse().unwrap()),
+            population: NonZeroUsize::new(population).unwrap(),
+            quantile: 0.25,
+            resample_probability: 0.25,
+            metric: None,
+        }
+        .build()
+        .unwrap()
+    }
+
+    fn obs(trial_id: u64, loss: Option<f64>) -> Observation {
+        let mut obs = Observation::new(ObservationId::new(trial_id), TrialId::new(trial_id));
+        let ty = FidelityParamType::new(0.0, 10.0, Some(1.0)).unwrap();
+        obs.insert_param(
+            ParamName::new("epoch".to_owned()),
+            ParamInstance::new(
+                ParamType::Num(NumParamType::Fidelity(ty)),
+                ParamValue::Num(FiniteF64::new(1.0).unwrap()),
+            ),
+        );
+        if let Some(loss) = loss {
+            obs.exit_status = Some(0);
+            obs.metrics.insert(
+                MetricName::new("loss".to_owned()),
+                MetricInstance::new(MetricType::Minimize, MetricValue::new(loss).unwrap()),
+            );
+        } else {
+            obs.exit_status = Some(1);
+        }
+        obs
+    }
+
+    #[test]
+    fn exploits_after_all_members_finish_a_step() -> anyhow::Result<()> {
+        let mut tuner = pbt(4);
+        for trial_id in 0..4 {
+            assert!(tuner.next_action().is_none());
+            tuner.start_trial(TrialId::new(trial_id));
+        }
+
+        for trial_id in 0..3 {
+            tuner.tell(&obs(trial_id, Some(trial_id as f64)))?;
+            assert!(matches!(
+                tuner.next_action(),
+                Some(Action::WaitObservations)
+            ));
+        }
+        tuner.tell(&obs(3, Some(3.0)))?;
+
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::CopyTrialDir { from, to })
+                if from == TrialId::new(0) && to == TrialId::new(3)
+        ));
+        for trial_id in 0..4 {
+            assert!(matches!(
+                tuner.next_action(),
+                Some(Action::ResumeTrial { trial_id: t }) if t == TrialId::new(trial_id)
+            ));
+        }
+        assert!(tuner.members.values().all(|m| m.step == 1));
+        Ok(())
+    }
+
+    #[test]
+    fn trials_not_started_by_the_tuner_are_not_members() -> anyhow::Result<()> {
+        let mut tuner = pbt(1);
+        assert!(tuner.next_action().is_none());
+        tuner.tell(&obs(100, Some(1.0)))?;
+        tuner.start_trial(TrialId::new(0));
+
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::FinishTrial { trial_id }) if trial_id == TrialId::new(100)
+        ));
+        tuner.tell(&obs(0, None))?;
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::FinishTrial { trial_id }) if trial_id == TrialId::new(0)
+        ));
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::QuitOptimization)
+        ));
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::param::{NumParamType, ParamName, ParamType, ParamValue, StrParamType};
+use crate::param::{ListParamType, NumParamType, ParamName, ParamType, ParamValue};
 use crate::rng::{ArcRng, RngSeed};
 use crate::trial::Observation;
 use crate::tuners::{Action, ActionQueue, Tune};
@@ -33,39 +33,20 @@ impl RandomTuner {
             actions: ActionQueue::new(),
         }
     }
-}
 
-impl Tune for RandomTuner {
-    fn ask(
-        &mut self,
-        _obs: &Observation,
-        _param_name: &ParamName,
-        param_type: &ParamType,
-    ) -> anyhow::Result<ParamValue> {
+    fn sample(&mut self, param_type: &ParamType) -> anyhow::Result<ParamValue> {
         let rng = &mut self.rng;
         match param_type {
-            ParamType::Str(StrParamType::Categorical(t)) => Ok(ParamValue::Str(
-                t.choices().get().choose(rng).expect("unreachable").clone(),
-            )),
-            ParamType::Str(StrParamType::Ordinal(t)) => Ok(ParamValue::Str(
-                t.choices().get().choose(rng).expect("unreachable").clone(),
-            )),
+            ParamType::Str(t) => Ok(ParamValue::Str(t.choice_from_unit(rng.gen()).clone())),
             ParamType::Num(NumParamType::Continous(t)) => {
-                if t.ln() {
-                    let v = rng.gen_range(t.range().min().get().ln(), t.range().max().get().ln());
-                    let v = FiniteF64::new(v.exp())?;
-                    Ok(ParamValue::Num(v))
-                } else {
-                    let v = rng.gen_range(t.range().min().get(), t.range().max().get());
-                    Ok(ParamValue::Num(FiniteF64::new(v).expect("unreachable")))
-                }
+                let v = FiniteF64::new(t.from_unit(rng.gen()))?;
+                Ok(ParamValue::Num(v))
             }
             ParamType::Num(NumParamType::Discrete(t)) => {
-                let n = rng.gen_range(0, t.count());
-                let v = t.range().min().get() + t.step().get() * n as f64;
-                let v = FiniteF64::new(v)?;
+                let v = FiniteF64::new(t.from_unit(rng.gen()))?;
                 Ok(ParamValue::Num(v))
             }
+            ParamType::Num(NumParamType::Int(t)) => Ok(ParamValue::Int(t.from_unit(rng.gen()))),
             ParamType::Num(NumParamType::Normal(t)) => {
                 let d = rand_distr::Normal::new(t.mean().get(), t.stddev().get())?;
                 let v = d.sample(rng);
@@ -73,8 +54,30 @@ impl Tune for RandomTuner {
                 Ok(ParamValue::Num(v))
             }
             ParamType::Num(NumParamType::Fidelity(t)) => Ok(ParamValue::Num(t.range().max())),
+            ParamType::List(ListParamType::Vector(t)) => (0..t.size())
+                .map(|_| self.sample(t.element()))
+                .collect::<anyhow::Result<_>>()
+                .map(ParamValue::List),
+            ParamType::List(ListParamType::Permutation(t)) => {
+                let mut items = t.items().get().to_vec();
+                items.shuffle(rng);
+                Ok(ParamValue::List(
+                    items.into_iter().map(ParamValue::Str).collect(),
+                ))
+            }
         }
     }
+}
+
+impl Tune for RandomTuner {
+    fn ask(
+        &mut self,
+        _obs: &Observation,
+        _param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        self.sample(param_type)
+    }
 
     fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
         self.actions.enqueue(Action::finish_trial(obs.trial_id));
@@ -85,3 +88,137 @@ impl Tune for RandomTuner {
         self.actions.next()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{
+        CategoricalParamType, ContinousParamType, DiscreteParamType, IntParamType, Prior,
+        StrParamType,
+    };
+    use std::collections::BTreeMap;
+
+    const SAMPLES: usize = 10000;
+
+    fn tuner() -> anyhow::Result<RandomTuner> {
+        RandomTunerSpec {
+            seed: Some("0".parse()?),
+        }
+        .build()
+    }
+
+    fn sample_histogram(ty: &ParamType) -> anyhow::Result<BTreeMap<String, usize>> {
+        let mut tuner = tuner()?;
+        let mut histogram = BTreeMap::new();
+        for _ in 0..SAMPLES {
+            *histogram.entry(tuner.sample(ty)?.to_string()).or_default() += 1;
+        }
+        Ok(histogram)
+    }
+
+    fn assert_frequency(histogram: &BTreeMap<String, usize>, value: &str, expected: f64) {
+        let count = histogram.get(value).copied().unwrap_or(0);
+        let frequency = count as f64 / SAMPLES as f64;
+        assert!(
+            (frequency - expected).abs() < 0.02,
+            "{}: {} != {} ({:?})",
+            value,
+            frequency,
+            expected,
+            histogram
+        );
+    }
+
+    #[test]
+    fn discrete_values_are_uniform_including_max() -> anyhow::Result<()> {
+        let ty = ParamType::Num(NumParamType::Discrete(DiscreteParamType::new(
+            0.0, 3.0, 1.0, false, None,
+        )?));
+        let histogram = sample_histogram(&ty)?;
+        assert_eq!(histogram.len(), 4);
+        for v in &["0", "1", "2", "3"] {
+            assert_frequency(&histogram, v, 0.25);
+        }
+
+        let ty = ParamType::Num(NumParamType::Int(IntParamType::new(1, 4, false)?));
+        let histogram = sample_histogram(&ty)?;
+        for v in &["1", "2", "3", "4"] {
+            assert_frequency(&histogram, v, 0.25);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn log_scale_values_follow_the_int_distribution() -> anyhow::Result<()> {
+        let discrete = ParamType::Num(NumParamType::Discrete(DiscreteParamType::new(
+            1.0, 4.0, 1.0, true, None,
+        )?));
+        let int = ParamType::Num(NumParamType::Int(IntParamType::new(1, 4, true)?));
+        for ty in &[discrete, int] {
+            let histogram = sample_histogram(ty)?;
+            for v in 1..=4 {
+                let expected = ((v + 1) as f64 / v as f64).ln() / 5f64.ln();
+                assert_frequency(&histogram, &v.to_string(), expected);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn choices_are_sampled_by_weight() -> anyhow::Result<()> {
+        let choices = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let ty = ParamType::Str(StrParamType::Categorical(CategoricalParamType::new(
+            choices.clone(),
+            Some(vec![3.0, 1.0, 1.0]),
+        )?));
+        let histogram = sample_histogram(&ty)?;
+        assert_frequency(&histogram, "a", 0.6);
+        assert_frequency(&histogram, "b", 0.2);
+        assert_frequency(&histogram, "c", 0.2);
+
+        let ty = ParamType::Str(StrParamType::Categorical(CategoricalParamType::new(
+            choices,
+            Some(vec![0.0, 1.0, 0.0]),
+        )?));
+        let histogram = sample_histogram(&ty)?;
+        assert_eq!(histogram.keys().collect::<Vec<_>>(), ["b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn priors_are_truncated_to_the_range() -> anyhow::Result<()> {
+        for &ln in &[false, true] {
+            let prior = Prior::new(2.0, 0.5)?;
+            let ty = ParamType::Num(NumParamType::Continous(ContinousParamType::new(
+                0.01,
+                100.0,
+                ln,
+                Some(prior),
+            )?));
+            let mut tuner = tuner()?;
+            let mut values = (0..SAMPLES)
+                .map(|_| Ok(tuner.sample(&ty)?.as_f64().expect("number")))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            assert!(values.iter().all(|v| (0.01..=100.0).contains(v)));
+
+            // The range is wide enough for the truncation not to move the median.
+            values.sort_by(|a, b| a.partial_cmp(b).expect("finite"));
+            let median = values[SAMPLES / 2];
+            assert!((median - 2.0).abs() < 0.1, "ln={}: median={}", ln, median);
+        }
+
+        // Most of the mass of this prior is outside the range, which is almost flat inside it.
+        let ty = ParamType::Num(NumParamType::Discrete(DiscreteParamType::new(
+            10.0,
+            11.0,
+            0.5,
+            false,
+            Some(Prior::new(0.0, 100.0)?),
+        )?));
+        let histogram = sample_histogram(&ty)?;
+        for v in &["10", "10.5", "11"] {
+            assert_frequency(&histogram, v, 1.0 / 3.0);
+        }
+        Ok(())
+    }
+}
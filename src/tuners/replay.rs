@@ -0,0 +1,89 @@
+use crate::param::{ParamName, ParamType, ParamValue};
+use crate::trial::{Observation, TrialId};
+use crate::tuners::{Action, ActionQueue, Tune};
+use anyhow::Context;
+use std::collections::BTreeMap;
+
+/// A tuner that runs a single trial with recorded parameters.
+#[derive(Debug)]
+pub struct ReplayTuner {
+    trial_id: TrialId,
+    params: BTreeMap<ParamName, ParamValue>,
+    started: bool,
+    actions: ActionQueue,
+}
+
+impl ReplayTuner {
+    /// `trial_id` is the ID of the recorded trial (only used in error messages).
+    pub fn new(trial_id: TrialId, params: BTreeMap<ParamName, ParamValue>) -> Self {
+        Self {
+            trial_id,
+            params,
+            started: false,
+            actions: ActionQueue::new(),
+        }
+    }
+}
+
+impl Tune for ReplayTuner {
+    fn ask(
+        &mut self,
+        _obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        let value = self.params.get(param_name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "the parameter {:?} isn't recorded in the trial {}",
+                param_name.get(),
+                self.trial_id.get()
+            )
+        })?;
+        param_type.normalize(value).with_context(|| {
+            format!(
+                "the recorded value of {:?} doesn't match the current type",
+                param_name.get()
+            )
+        })
+    }
+
+    fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        self.actions.enqueue(Action::finish_trial(obs.trial_id));
+        Ok(())
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        if let Some(action) = self.actions.next() {
+            Some(action)
+        } else if self.started {
+            Some(Action::QuitOptimization)
+        } else {
+            self.started = true;
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::ObservationId;
+
+    #[test]
+    fn finishes_the_trial_then_quits() -> anyhow::Result<()> {
+        let mut tuner = ReplayTuner::new(TrialId::new(3), BTreeMap::new());
+        assert!(tuner.next_action().is_none());
+
+        let obs = Observation::new(ObservationId::new(0), TrialId::new(0));
+        tuner.tell(&obs)?;
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::FinishTrial { trial_id }) if trial_id == TrialId::new(0)
+        ));
+        assert!(matches!(
+            tuner.next_action(),
+            Some(Action::QuitOptimization)
+        ));
+        Ok(())
+    }
+}
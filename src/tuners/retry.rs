@@ -49,8 +49,6 @@ impl Tune for RetryTuner {
     }
 
     fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
-        assert!(obs.exit_status.is_some());
-
         if obs.is_succeeded() {
             let obs = if let Some(mut orig_obs) =
                 self.retryings.remove(&obs.trial_id).map(|x| x.obs)
@@ -61,6 +59,7 @@ impl Tune for RetryTuner {
                     obs.params, orig_obs.params);
                 orig_obs.metrics = obs.metrics.clone();
                 orig_obs.exit_status = obs.exit_status;
+                orig_obs.failure = obs.failure;
                 orig_obs
             } else {
                 obs.clone()
@@ -87,6 +86,10 @@ impl Tune for RetryTuner {
     fn next_action(&mut self) -> Option<Action> {
         self.actions.next().or_else(|| self.tuner.next_action())
     }
+
+    fn start_trial(&mut self, trial_id: TrialId) {
+        self.tuner.start_trial(trial_id)
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,93 @@
+use crate::param::{NumParamType, ParamName, ParamType, ParamValue};
+use crate::stats::inverse_normal_cdf;
+use crate::trial::{Observation, TrialId};
+use crate::tuners::{Action, ActionQueue, Tune};
+use crate::types::FiniteF64;
+use std::collections::HashMap;
+
+/// A sequence of points in the unit hypercube.
+pub trait PointSequence {
+    /// Returns the `dimension`-th coordinate of the `index`-th point (in the interval `[0, 1)`).
+    fn sample(&mut self, index: u64, dimension: usize) -> anyhow::Result<f64>;
+}
+
+/// A tuner that assigns each trial a point of a `PointSequence`.
+///
+/// Parameters are mapped to the dimensions of the point in the order they are first asked.
+#[derive(Debug)]
+pub struct SequenceTuner<S> {
+    sequence: S,
+    dimensions: HashMap<ParamName, usize>,
+    trials: HashMap<TrialId, u64>,
+    next_index: u64,
+    actions: ActionQueue,
+}
+
+impl<S: PointSequence> SequenceTuner<S> {
+    pub fn new(sequence: S) -> Self {
+        Self {
+            sequence,
+            dimensions: HashMap::new(),
+            trials: HashMap::new(),
+            next_index: 0,
+            actions: ActionQueue::new(),
+        }
+    }
+}
+
+impl<S: PointSequence> Tune for SequenceTuner<S> {
+    fn ask(
+        &mut self,
+        obs: &Observation,
+        param_name: &ParamName,
+        param_type: &ParamType,
+    ) -> anyhow::Result<ParamValue> {
+        let next_index = &mut self.next_index;
+        let index = *self.trials.entry(obs.trial_id).or_insert_with(|| {
+            *next_index += 1;
+            *next_index - 1
+        });
+        let next_dimension = self.dimensions.len();
+        let dimension = *self
+            .dimensions
+            .entry(param_name.clone())
+            .or_insert(next_dimension);
+        let u = self.sequence.sample(index, dimension)?;
+        unit_to_value(param_type, u)
+    }
+
+    fn tell(&mut self, obs: &Observation) -> anyhow::Result<()> {
+        self.trials.remove(&obs.trial_id);
+        self.actions.enqueue(Action::finish_trial(obs.trial_id));
+        Ok(())
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        self.actions.next()
+    }
+}
+
+/// Maps `u` in the interval `[0, 1)` to a value of `param_type`.
+pub fn unit_to_value(param_type: &ParamType, u: f64) -> anyhow::Result<ParamValue> {
+    match param_type {
+        ParamType::Str(t) => Ok(ParamValue::Str(t.choice_from_unit(u).clone())),
+        ParamType::Num(NumParamType::Continous(t)) => {
+            Ok(ParamValue::Num(FiniteF64::new(t.from_unit(u))?))
+        }
+        ParamType::Num(NumParamType::Discrete(t)) => {
+            Ok(ParamValue::Num(FiniteF64::new(t.from_unit(u))?))
+        }
+        ParamType::Num(NumParamType::Int(t)) => Ok(ParamValue::Int(t.from_unit(u))),
+        ParamType::Num(NumParamType::Normal(t)) => {
+            let u = u.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+            let v = t.mean().get() + t.stddev().get() * inverse_normal_cdf(u);
+            Ok(ParamValue::Num(FiniteF64::new(v)?))
+        }
+        ParamType::Num(NumParamType::Fidelity(t)) => Ok(ParamValue::Num(t.range().max())),
+        ParamType::List(_) => {
+            anyhow::bail!(
+                "vector and permutation parameters are only supported by the random tuner"
+            )
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::rng::{ArcRng, RngSeed};
+use crate::tuners::sequence::{PointSequence, SequenceTuner};
+use rand::Rng;
+
+#[derive(Debug, Clone, Default, clap::Args, serde::Serialize, serde::Deserialize)]
+pub struct SobolTunerSpec {
+    #[clap(long)]
+    pub seed: Option<RngSeed>,
+}
+
+impl SobolTunerSpec {
+    pub fn build(&self) -> anyhow::Result<SequenceTuner<SobolSequence>> {
+        let mut rng = ArcRng::new(self.seed.unwrap_or_default());
+        Ok(SequenceTuner::new(SobolSequence { seed: rng.gen() }))
+    }
+}
+
+/// Owen-scrambled Sobol sequence.
+#[derive(Debug)]
+pub struct SobolSequence {
+    seed: u32,
+}
+
+impl PointSequence for SobolSequence {
+    fn sample(&mut self, index: u64, dimension: usize) -> anyhow::Result<f64> {
+        anyhow::ensure!(
+            index < (1 << 16),
+            "the Sobol sequence supports up to {} points",
+            1 << 16
+        );
+        anyhow::ensure!(
+            dimension < sobol_burley::NUM_DIMENSIONS as usize,
+            "the Sobol sequence supports up to {} parameters",
+            sobol_burley::NUM_DIMENSIONS
+        );
+        Ok(f64::from(sobol_burley::sample(
+            index as u32,
+            dimension as u32,
+            self.seed,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_are_stratified() -> anyhow::Result<()> {
+        let mut sobol = SobolSequence { seed: 123 };
+        for dimension in 0..3 {
+            let mut strata = (0..8)
+                .map(|i| sobol.sample(i, dimension))
+                .map(|u| u.map(|u| (u * 8.0).floor() as usize))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            strata.sort_unstable();
+            assert_eq!(strata, [0, 1, 2, 3, 4, 5, 6, 7]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_range_points_are_rejected() {
+        let mut sobol = SobolSequence { seed: 0 };
+        assert!(sobol.sample(1 << 16, 0).is_err());
+        assert!(sobol
+            .sample(0, sobol_burley::NUM_DIMENSIONS as usize)
+            .is_err());
+    }
+}
@@ -69,6 +69,10 @@ impl InclusiveRange {
     pub fn width(self) -> FiniteF64 {
         FiniteF64::new(self.max.get() - self.min.get()).expect("unreachable")
     }
+
+    pub fn contains(self, x: FiniteF64) -> bool {
+        self.min <= x && x <= self.max
+    }
 }
 
 impl TryFrom<UncheckedInclusiveRange> for InclusiveRange {
@@ -176,8 +180,110 @@ impl ElapsedSeconds {
     }
 }
 
+impl std::str::FromStr for ElapsedSeconds {
+    type Err = anyhow::Error;
+
+    /// Parses a string such as `90`, `90s`, `30m`, `8h` or `1d`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = match s.char_indices().last() {
+            Some((i, 's')) => (&s[..i], 1.0),
+            Some((i, 'm')) => (&s[..i], 60.0),
+            Some((i, 'h')) => (&s[..i], 60.0 * 60.0),
+            Some((i, 'd')) => (&s[..i], 24.0 * 60.0 * 60.0),
+            _ => (s, 1.0),
+        };
+        let seconds = number
+            .parse::<f64>()
+            .map_err(|_| anyhow::anyhow!("invalid duration: {:?}", s))?
+            * unit;
+        anyhow::ensure!(
+            seconds.is_finite() && seconds >= 0.0,
+            "invalid duration: {:?}",
+            s
+        );
+        Ok(Self(seconds))
+    }
+}
+
 impl From<Duration> for ElapsedSeconds {
     fn from(f: Duration) -> Self {
         Self(f.as_secs_f64())
     }
 }
+
+/// Number of bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub const fn new(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::str::FromStr for ByteSize {
+    type Err = anyhow::Error;
+
+    /// Parses a string such as `1024`, `512K`, `100M` or `8G` (the units are powers of 1024).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let unit: u64 = match s[number.len()..].to_ascii_uppercase().as_str() {
+            "" | "B" => 1,
+            "K" | "KB" | "KIB" => 1 << 10,
+            "M" | "MB" | "MIB" => 1 << 20,
+            "G" | "GB" | "GIB" => 1 << 30,
+            "T" | "TB" | "TIB" => 1 << 40,
+            _ => anyhow::bail!("unknown unit of a byte size: {:?}", s),
+        };
+        let bytes = number
+            .parse::<f64>()
+            .map_err(|_| anyhow::anyhow!("invalid byte size: {:?}", s))?
+            * unit as f64;
+        anyhow::ensure!(
+            bytes.is_finite() && bytes >= 0.0 && bytes < u64::MAX as f64,
+            "invalid byte size: {:?}",
+            s
+        );
+        Ok(Self(bytes as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_byte_size() -> anyhow::Result<()> {
+        assert_eq!("1024".parse::<ByteSize>()?.get(), 1024);
+        assert_eq!("512K".parse::<ByteSize>()?.get(), 512 << 10);
+        assert_eq!("100mb".parse::<ByteSize>()?.get(), 100 << 20);
+        assert_eq!("1.5G".parse::<ByteSize>()?.get(), 3 << 29);
+        assert_eq!("2TiB".parse::<ByteSize>()?.get(), 2 << 40);
+
+        assert!("".parse::<ByteSize>().is_err());
+        assert!("8X".parse::<ByteSize>().is_err());
+        assert!("-1K".parse::<ByteSize>().is_err());
+        assert!("G".parse::<ByteSize>().is_err());
+        assert!("99999999999T".parse::<ByteSize>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_elapsed_seconds() -> anyhow::Result<()> {
+        assert_eq!("90".parse::<ElapsedSeconds>()?.get(), 90.0);
+        assert_eq!("1.5s".parse::<ElapsedSeconds>()?.get(), 1.5);
+        assert_eq!("30m".parse::<ElapsedSeconds>()?.get(), 1800.0);
+        assert_eq!("8h".parse::<ElapsedSeconds>()?.get(), 28800.0);
+        assert_eq!("1d".parse::<ElapsedSeconds>()?.get(), 86400.0);
+
+        assert!("".parse::<ElapsedSeconds>().is_err());
+        assert!("1w".parse::<ElapsedSeconds>().is_err());
+        assert!("-1s".parse::<ElapsedSeconds>().is_err());
+        assert!("infh".parse::<ElapsedSeconds>().is_err());
+        Ok(())
+    }
+}